@@ -0,0 +1,45 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn abs_thresholds_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/abs-thresholds.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    let gamepad = Arc::new(DeviceInfo {
+        path: "/dev/input/event9".to_string(),
+        name: "Gamepad".to_string(),
+        vendor: 0,
+        product: 0,
+        abs_ranges: vec![(ABS_X.event_code, -32768, 32767), (ABS_Y.event_code, -32768, 32767)],
+        gamepad: true,
+    });
+
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, -10000).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, -20000).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 0).to_input_ev()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, TYPE_DOWN).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_A, TYPE_UP).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // axes without thresholds are passed through
+    let rz = parse_abs_axis("rz")?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(rz, 100).to_input_ev()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(rz, 100).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn auto_shift_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/auto-shift.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // tapping types the key unshifted
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+    ]);
+
+    // holding types the key shifted, auto-repeat repeats the shifted key
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_B, 2)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 2).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+    ]);
+
+    // a quick tap while another key is held shifted is still held back, the shift auto shift pressed doesn't
+    // count as a held modifier
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_C, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+    ]);
+
+    // excluded keys are passed through right away
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn debounce_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/debounce.m2";
+
+    let mut api = test_script(params).await?;
+    sleep(100);
+
+    // the second press right after the release is dropped
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+
+    // devices can have their own thresholds
+    let old_keyboard = Arc::new(DeviceInfo {
+        path: "/dev/input/event7".to_string(),
+        name: "Old Keyboard".to_string(),
+        vendor: 0,
+        product: 0,
+        abs_ranges: vec![],
+        gamepad: false,
+    });
+
+    api.write_device_event(old_keyboard.clone(), KeyAction::new(*KEY_E, 1).to_input_ev()).await?;
+    api.write_device_event(old_keyboard.clone(), KeyAction::new(*KEY_E, 0).to_input_ev()).await?;
+    sleep(100);
+    api.write_device_event(old_keyboard.clone(), KeyAction::new(*KEY_E, 1).to_input_ev()).await?;
+    api.write_device_event(old_keyboard.clone(), KeyAction::new(*KEY_E, 0).to_input_ev()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_E, 1).to_input_ev(),
+        KeyAction::new(*KEY_E, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_stdout().await, "suppressed events: 4\n");
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn device_conditions_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/device-conditions.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    let macro_pad = Arc::new(DeviceInfo {
+        path: "/dev/input/event7".to_string(),
+        name: "Macro Pad".to_string(),
+        vendor: 0x1234,
+        product: 0x5678,
+        abs_ranges: vec![],
+        gamepad: false,
+    });
+
+    api.write_device_event(macro_pad.clone(), KeyAction::new(*KEY_A, 1).to_input_ev()).await?;
+    api.write_device_event(macro_pad.clone(), KeyAction::new(*KEY_A, 0).to_input_ev()).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_stdout().await, "pressed on Macro Pad (/dev/input/event7)\n");
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
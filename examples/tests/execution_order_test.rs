@@ -0,0 +1,42 @@
+use crate::*;
+use crate::tests::*;
+
+fn click(key: Key) -> Vec<InputEvent> {
+    vec![
+        KeyAction::new(key, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(key, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn execution_order_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/execution-order.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // same key, ordered
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(500);
+
+    assert_eq!(api.collect_output_ev().await, vec![click(*KEY_B), click(*KEY_C)].concat());
+
+    // the interleaving of concurrent mappings depends on timing, it is covered by the executor tests
+
+    // different keys, globally ordered
+    api.write_action(KeyAction::new(*KEY_G, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_G, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    sleep(500);
+
+    assert_eq!(api.collect_output_ev().await, vec![click(*KEY_D), click(*KEY_E)].concat());
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn gamepad_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/gamepad.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    let gamepad = Arc::new(DeviceInfo {
+        path: "/dev/input/event9".to_string(),
+        name: "Gamepad".to_string(),
+        vendor: 0,
+        product: 0,
+        abs_ranges: vec![(ABS_X.event_code, 0, 255), (ABS_Y.event_code, 0, 255)],
+        gamepad: true,
+    });
+
+    api.write_device_event(gamepad.clone(), KeyAction::new(*BTN_SOUTH, TYPE_DOWN).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*BTN_SOUTH, TYPE_UP).to_input_ev()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*BTN_EAST, TYPE_DOWN).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_EAST, TYPE_UP).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // the stick is scaled to the range of the virtual gamepad, the deadzone swallows small movements
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 130).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), SYN_REPORT.clone()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 255).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), SYN_REPORT.clone()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 128).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), SYN_REPORT.clone()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        SYN_REPORT.clone(),
+        KeyAction::new(*ABS_X, 32767).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*ABS_X, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // keys push the stick while held
+    api.write_action(KeyAction::new(*KEY_UP, TYPE_DOWN)).await?;
+    api.write_action(KeyAction::new(*KEY_UP, TYPE_UP)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*ABS_Y, -32767).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*ABS_Y, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn gestures_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/gestures.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    api.write_action(KeyAction::new(*BTN_RIGHT, 1)).await?;
+    api.write_action(KeyAction::new(*REL_Y, 60)).await?;
+    api.write_action(KeyAction::new(*REL_X, 60)).await?;
+    api.write_action(KeyAction::new(*BTN_RIGHT, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_W, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_W, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // the click is replayed along with the movement if no gesture was recognized
+    api.write_action(KeyAction::new(*BTN_RIGHT, 1)).await?;
+    api.write_action(KeyAction::new(*REL_X, 10)).await?;
+    api.write_action(KeyAction::new(*BTN_RIGHT, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*BTN_RIGHT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*REL_X, 10).to_input_ev(),
+        KeyAction::new(*BTN_RIGHT, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
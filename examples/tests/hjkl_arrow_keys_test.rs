@@ -31,4 +31,91 @@ async fn hjkl_arrow_keys_test() -> Result<()> {
     api.stop().await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn hjkl_arrow_keys_modifier_released_first_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/hjkl-arrow-keys.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    // the release of 'h' goes to the mapping that handled the press, even though alt isn't held anymore
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // an unmapped press isn't paired with a mapping that matches the release
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_H, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_H, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn hjkl_arrow_keys_two_keyboards_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/hjkl-arrow-keys.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    let second_keyboard = Arc::new(DeviceInfo {
+        path: "/dev/input/event7".to_string(),
+        name: "Second Keyboard".to_string(),
+        vendor: 0x1234,
+        product: 0x5678,
+        abs_ranges: vec![],
+        gamepad: false,
+    });
+
+    // holding 'h' on another keyboard doesn't take over the release of the mapped 'h'
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    api.write_device_event(second_keyboard.clone(), KeyAction::new(*KEY_H, 1).to_input_ev()).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_device_event(second_keyboard.clone(), KeyAction::new(*KEY_H, 0).to_input_ev()).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_H, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_H, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
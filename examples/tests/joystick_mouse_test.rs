@@ -0,0 +1,68 @@
+use crate::*;
+use crate::tests::*;
+
+// sums up the movement on an axis, the number of events depends on the timing of the ticks
+fn distance(events: &[InputEvent], axis: Key) -> i32 {
+    events.iter().filter(|ev| ev.event_code == axis.event_code).map(|ev| ev.value).sum()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn joystick_mouse_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/joystick-mouse.m2";
+
+    let mut api = test_script(params).await?;
+    sleep(100);
+
+    let gamepad = Arc::new(DeviceInfo {
+        path: "/dev/input/event9".to_string(),
+        name: "Gamepad".to_string(),
+        vendor: 0,
+        product: 0,
+        abs_ranges: vec![
+            (ABS_X.event_code, -32768, 32767),
+            (ABS_Y.event_code, -32768, 32767),
+            (ABS_RX.event_code, -32768, 32767),
+            (ABS_RY.event_code, -32768, 32767),
+        ],
+        gamepad: true,
+    });
+
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 32767).to_input_ev()).await?;
+    sleep(300);
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_X, 0).to_input_ev()).await?;
+    sleep(100);
+
+    let output = api.collect_output_ev().await;
+    assert!(distance(&output, *REL_X) > 40);
+    assert_eq!(distance(&output, *REL_Y), 0);
+
+    // the pointer stops once the stick is centered
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    // pushing the right stick up scrolls up
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_RY, -32768).to_input_ev()).await?;
+    sleep(300);
+    api.write_device_event(gamepad.clone(), KeyAction::new(*ABS_RY, 0).to_input_ev()).await?;
+    sleep(100);
+
+    let output = api.collect_output_ev().await;
+    assert!(distance(&output, *REL_WHEEL) > 0);
+
+    // buttons act as mouse buttons
+    api.write_device_event(gamepad.clone(), KeyAction::new(*BTN_SOUTH, TYPE_DOWN).to_input_ev()).await?;
+    api.write_device_event(gamepad.clone(), KeyAction::new(*BTN_SOUTH, TYPE_UP).to_input_ev()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*BTN_LEFT, TYPE_DOWN).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_LEFT, TYPE_UP).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn leds_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/leds.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // the layer LED is turned off initially
+    sleep(50);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*LED_SCROLLL, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_F1, TYPE_DOWN)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, TYPE_UP)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*LED_SCROLLL, 1).to_input_ev(),
+    ]);
+
+    // the lock state is mirrored onto the LEDs
+    api.set_lock_state(*LED_NUML, true).await?;
+    api.write_action(KeyAction::new(*KEY_F2, TYPE_DOWN)).await?;
+    api.write_action(KeyAction::new(*KEY_F2, TYPE_UP)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*LED_NUML, 1).to_input_ev(),
+    ]);
+    assert_eq!(api.collect_stdout().await, "numlock is on\n");
+
+    // LEDs set by the script don't follow the lock state until they are reset
+    api.write_action(KeyAction::new(*KEY_F3, TYPE_DOWN)).await?;
+    api.write_action(KeyAction::new(*KEY_F3, TYPE_UP)).await?;
+    sleep(100);
+    api.set_lock_state(*LED_CAPSL, true).await?;
+    api.set_lock_state(*LED_CAPSL, false).await?;
+    api.write_action(KeyAction::new(*KEY_F4, TYPE_DOWN)).await?;
+    api.write_action(KeyAction::new(*KEY_F4, TYPE_UP)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*LED_CAPSL, 1).to_input_ev(),
+        KeyAction::new(*LED_CAPSL, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn macros_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/macros.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_F2, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F2, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_F3, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F3, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn macros_mapped_key_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/macros.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_F2, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F2, 0)).await?;
+    sleep(50);
+
+    let mapped_output = vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ];
+    assert_eq!(api.collect_output_ev().await, mapped_output);
+
+    // the replayed keys are mapped again instead of typing the recorded keys
+    api.write_action(KeyAction::new(*KEY_F3, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F3, 0)).await?;
+    sleep(300);
+
+    assert_eq!(api.collect_output_ev().await, mapped_output);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -1,4 +1,28 @@
 mod functions_test;
 mod math_test;
 mod hjkl_arrow_keys_test;
-mod control_statements_test;
\ No newline at end of file
+mod control_statements_test;
+mod layers_test;
+mod window_scopes_test;
+mod device_conditions_test;
+mod modifier_wildcards_test;
+mod side_specific_modifiers_test;
+mod custom_modifiers_test;
+mod execution_order_test;
+mod hold_to_repeat_test;
+mod sticky_keys_test;
+mod tap_dance_test;
+mod auto_shift_test;
+mod macros_test;
+mod turbo_test;
+mod debounce_test;
+mod mouse_wheel_test;
+mod mouse_output_test;
+mod mouse_keys_test;
+mod scroll_emulation_test;
+mod pointer_transform_test;
+mod gestures_test;
+mod abs_thresholds_test;
+mod gamepad_test;
+mod joystick_mouse_test;
+mod leds_test;
@@ -0,0 +1,40 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn modifier_wildcards_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/modifier-wildcards.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        // '*a::b' releases shift
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        // '~*c::d' keeps shift pressed
+        KeyAction::new(*KEY_D, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_D, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::*;
+use crate::tests::*;
+
+// sums up the movement on an axis, the number of events depends on the timing of the ticks
+fn distance(events: &[InputEvent], axis: Key) -> i32 {
+    events.iter().filter(|ev| ev.event_code == axis.event_code).map(|ev| ev.value).sum()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_keys_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse-keys.m2";
+
+    let mut api = test_script(params).await?;
+    sleep(100);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    sleep(50);
+
+    api.write_action(KeyAction::new(*KEY_L, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_L, 0)).await?;
+    sleep(100);
+
+    let output = api.collect_output_ev().await;
+    assert!(distance(&output, *REL_X) > 40);
+    assert_eq!(distance(&output, *REL_Y), 0);
+    assert_eq!(output.last(), Some(&SYN_REPORT.clone()));
+
+    // the pointer stops once the key is released
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    // or if the layer is left while the key is held
+    api.write_action(KeyAction::new(*KEY_K, 1)).await?;
+    sleep(100);
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    sleep(100);
+    let output = api.collect_output_ev().await;
+    assert!(distance(&output, *REL_Y) < 0);
+
+    // the release goes to the layer mapping that handled the press
+    sleep(100);
+    api.write_action(KeyAction::new(*KEY_K, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_keys_drag_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse-keys.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    for _ in 0..2 {
+        api.write_action(KeyAction::new(*KEY_V, 1)).await?;
+        api.write_action(KeyAction::new(*KEY_V, 0)).await?;
+    }
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*BTN_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
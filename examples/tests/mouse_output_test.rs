@@ -0,0 +1,38 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_output_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse-output.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+
+    for key in [*KEY_F1, *KEY_F2, *KEY_F3, *KEY_F4].iter() {
+        api.write_action(KeyAction::new(*key, 1)).await?;
+        api.write_action(KeyAction::new(*key, 0)).await?;
+    }
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_X, -20).to_input_ev(),
+        KeyAction::new(*REL_Y, 10).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*REL_WHEEL_HI_RES, 120).to_input_ev(),
+        KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*REL_WHEEL_HI_RES, -60).to_input_ev(),
+        KeyAction::new(*REL_HWHEEL_HI_RES, 240).to_input_ev(),
+        KeyAction::new(*REL_HWHEEL, 2).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_MIDDLE, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_MIDDLE, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_wheel_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse-wheel.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    api.write_action(KeyAction::new(*REL_WHEEL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_VOLUME_UP, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_VOLUME_UP, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ]);
+
+    // the high resolution event of the frame triggers the mapping, the regular wheel event is dropped
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL_HI_RES, 120)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_VOLUME_UP, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_VOLUME_UP, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ]);
+
+    // fractions of a notch add up to a single trigger
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL_HI_RES, -60)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL_HI_RES, -60)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL, -1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_VOLUME_DOWN, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_VOLUME_DOWN, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*REL_HWHEEL, -2)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![]);
+    assert_eq!(api.collect_stdout().await, "horizontal scroll: -2\n");
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_move_custom_modifier_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse-wheel.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    // movement is only mapped while the custom modifier is held
+    api.write_action(KeyAction::new(*REL_X, 3)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*REL_X, 5)).await?;
+    api.write_action(KeyAction::new(*REL_Y, 5)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(200);
+
+    // the custom modifier isn't tapped since it was combined with the movement
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_X, 3).to_input_ev(),
+        KeyAction::new(*REL_Y, 5).to_input_ev(),
+    ]);
+    assert_eq!(api.collect_stdout().await, "moved by: 5\n");
+
+    api.stop().await;
+
+    Ok(())
+}
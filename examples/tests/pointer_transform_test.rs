@@ -0,0 +1,49 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pointer_transform_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/pointer-transform.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    let travel_mouse = Arc::new(DeviceInfo {
+        path: "/dev/input/event9".to_string(),
+        name: "Travel Mouse".to_string(),
+        vendor: 0,
+        product: 0,
+        abs_ranges: vec![],
+        gamepad: false,
+    });
+
+    api.write_device_event(travel_mouse.clone(), KeyAction::new(*REL_X, 1).to_input_ev()).await?;
+    api.write_device_event(travel_mouse.clone(), KeyAction::new(*REL_Y, 1).to_input_ev()).await?;
+    api.write_device_event(travel_mouse.clone(), SYN_REPORT.clone()).await?;
+    api.write_device_event(travel_mouse.clone(), KeyAction::new(*REL_WHEEL, 1).to_input_ev()).await?;
+    api.write_device_event(travel_mouse.clone(), SYN_REPORT.clone()).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_X, 2).to_input_ev(),
+        KeyAction::new(*REL_Y, -2).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*REL_WHEEL, 3).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // other devices aren't changed
+    api.write_action(KeyAction::new(*REL_X, 1)).await?;
+    api.write_action(KeyAction::new(*REL_WHEEL, 1)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_X, 1).to_input_ev(),
+        KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
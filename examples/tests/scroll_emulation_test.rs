@@ -0,0 +1,75 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn scroll_emulation_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/scroll-emulation.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    api.write_action(KeyAction::new(*BTN_MIDDLE, 1)).await?;
+    api.write_action(KeyAction::new(*REL_Y, 15)).await?;
+    // the horizontal movement is dropped since the vertical axis is locked
+    api.write_action(KeyAction::new(*REL_X, 5)).await?;
+    api.write_action(KeyAction::new(*BTN_MIDDLE, 0)).await?;
+    api.write_action(KeyAction::new(*REL_X, 5)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_WHEEL_HI_RES, 120).to_input_ev(),
+        KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+        KeyAction::new(*REL_X, 5).to_input_ev(),
+    ]);
+
+    // the button is clicked if the pointer didn't move
+    api.write_action(KeyAction::new(*BTN_MIDDLE, 1)).await?;
+    api.write_action(KeyAction::new(*BTN_MIDDLE, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*BTN_MIDDLE, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*BTN_MIDDLE, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn scroll_emulation_key_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/scroll-emulation-key.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // the key repeats while it's held, the repeats are dropped along with the press
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 2)).await?;
+    api.write_action(KeyAction::new(*REL_Y, -40)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*REL_WHEEL_HI_RES, 240).to_input_ev(),
+        KeyAction::new(*REL_WHEEL, 2).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_CAPSLOCK, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_CAPSLOCK, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn side_specific_modifiers_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/side-specific-modifiers.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    // right ctrl doesn't trigger '<^j'
+    api.write_action(KeyAction::new(*KEY_RIGHT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_RIGHT_CTRL, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_RIGHT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_J, 1).to_input_ev(),
+        KeyAction::new(*KEY_J, 0).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_CTRL, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        KeyAction::new(*KEY_DOWN, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_DOWN, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+    ]);
+
+    // AltGr as trigger
+    api.write_action(KeyAction::new(*KEY_RIGHT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_Q, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_Q, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_RIGHT_ALT, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_RIGHT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_RIGHT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_ALT, 0).to_input_ev(),
+    ]);
+
+    // AltGr as output
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_RIGHT_ALT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_E, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_E, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_RIGHT_ALT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
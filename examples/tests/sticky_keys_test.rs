@@ -0,0 +1,112 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sticky_modifiers_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/sticky-keys.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+
+    // tapped once, applies to the next key
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+    ]);
+
+    // tapped twice, stays held until tapped again
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_CTRL, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+    ]);
+
+    // held modifiers aren't sticky
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_SHIFT, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn caps_word_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/sticky-keys.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_MINUS, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_MINUS, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_SPACE, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_SPACE, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        // word characters that aren't letters aren't shifted
+        KeyAction::new(*KEY_MINUS, 1).to_input_ev(),
+        KeyAction::new(*KEY_MINUS, 0).to_input_ev(),
+        // space ends caps word
+        KeyAction::new(*KEY_SPACE, 1).to_input_ev(),
+        KeyAction::new(*KEY_SPACE, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
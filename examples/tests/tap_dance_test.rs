@@ -0,0 +1,85 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tap_dance_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/tap-dance.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // a single tap resolves after the timeout
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    sleep(300);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_ESC, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_ESC, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // a double tap toggles the navigation layer
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tap_dance_hold_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/tap-dance.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    // holding past the timeout
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // pressing another key while held resolves the hold right away, the buffered key follows
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    sleep(50);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn turbo_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/turbo.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(50);
+
+    let click = vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ];
+
+    // repeats while the key is held
+    api.write_action(KeyAction::new(*KEY_F9, 1)).await?;
+    sleep(230);
+    api.write_action(KeyAction::new(*KEY_F9, 0)).await?;
+    sleep(50);
+
+    let output = api.collect_output_ev().await;
+    assert!(output.len() >= 2 * click.len(), "expected at least 2 repetitions, got: {:?}", output);
+    assert!(output.chunks(click.len()).all(|chunk| chunk == &click[..]));
+
+    // the repetition stops once the key is released
+    sleep(300);
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    api.stop().await;
+
+    Ok(())
+}
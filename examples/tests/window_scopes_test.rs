@@ -0,0 +1,58 @@
+use crate::*;
+use crate::tests::*;
+
+fn window(class: &str, name: &str) -> ActiveWindowInfo {
+    ActiveWindowInfo { class: class.to_string(), instance: class.to_lowercase(), name: name.to_string() }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn window_scopes_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/window-scopes.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(200);
+
+    // no matching window, the key passes through
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+
+    api.set_active_window(window("firefox", "Mozilla Firefox")).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+
+    // the more specific mapping wins
+    api.set_active_window(window("firefox", "Cats - YouTube")).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+
+    api.set_active_window(window("Alacritty", "terminal")).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F1, 0)).await?;
+    sleep(200);
+
+    let output_ev = api.collect_output_ev().await;
+
+    assert_eq!(output_ev, vec![
+        KeyAction::new(*KEY_F1, 1).to_input_ev(),
+        KeyAction::new(*KEY_F1, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_T, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_T, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_K, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_K, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_F1, 1).to_input_ev(),
+        KeyAction::new(*KEY_F1, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use evdev_rs::enums::EventType;
+
+use crate::*;
+
+struct AbsThreshold {
+    axis: Key,
+    // the position past which the key is held, in percent of the distance between the axis center and its end,
+    // negative values point towards the minimum
+    percent: f64,
+    key: Key,
+}
+
+/// Turns absolute axis positions into key presses, e.g. pushing a stick to the left past 50% holds a key.
+///
+/// Axes that have thresholds are consumed, all other absolute events pass through.
+pub struct AbsThresholds {
+    thresholds: Vec<AbsThreshold>,
+    // the thresholds that are currently crossed, keyed by device path and threshold index
+    active: HashSet<(String, usize)>,
+}
+
+impl AbsThresholds {
+    pub fn new() -> Self {
+        AbsThresholds { thresholds: vec![], active: HashSet::new() }
+    }
+
+    pub fn insert(&mut self, axis: Key, percent: f64, key: Key) {
+        self.thresholds.push(AbsThreshold { axis, percent, key });
+    }
+
+    /// Gets the events an input event turned into.
+    pub fn handle_event(&mut self, ev: &InputEvent, device: &DeviceInfo) -> Vec<InputEvent> {
+        if !self.thresholds.iter().any(|threshold| threshold.axis.event_code == ev.event_code) {
+            return vec![ev.clone()];
+        }
+
+        let (min, max) = match device.abs_range(&ev.event_code) {
+            Some(range) => range,
+            None => return vec![ev.clone()],
+        };
+        let center = (min + max) as f64 / 2.0;
+        let half = (max - min) as f64 / 2.0;
+
+        let mut events = vec![];
+        for (idx, threshold) in self.thresholds.iter().enumerate() {
+            if threshold.axis.event_code != ev.event_code { continue; }
+
+            let position = center + half * threshold.percent / 100.0;
+            let crossed = if threshold.percent < 0.0 {
+                ev.value as f64 <= position
+            } else {
+                ev.value as f64 >= position
+            };
+
+            let id = (device.path.clone(), idx);
+            let value = match (crossed, self.active.contains(&id)) {
+                (true, false) => {
+                    self.active.insert(id);
+                    TYPE_DOWN
+                }
+                (false, true) => {
+                    self.active.remove(&id);
+                    TYPE_UP
+                }
+                _ => continue,
+            };
+            events.push(KeyAction::new(threshold.key, value).to_input_ev());
+            events.push(SYN_REPORT.clone());
+        }
+        events
+    }
+}
+
+/// Parses an absolute axis name, the 'ABS_' prefix is optional (e.g. 'x', 'rz', 'ABS_HAT0X').
+pub fn parse_abs_axis(name: &str) -> Result<Key> {
+    let name = name.to_uppercase();
+    let name = if name.starts_with("ABS_") { name } else { format!("ABS_{}", name) };
+    Key::from_str(&EventType::EV_ABS, &name)
+        .map_err(|_| anyhow!("unknown absolute axis '{}'", name))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn stick() -> DeviceInfo {
+        DeviceInfo { abs_ranges: vec![(ABS_X.event_code, 0, 255)], ..device("/dev/input/event0", "gamepad") }
+    }
+
+    #[test]
+    fn test_parse_abs_axis() {
+        assert_eq!(parse_abs_axis("x").unwrap(), *ABS_X);
+        assert_eq!(parse_abs_axis("ABS_X").unwrap(), *ABS_X);
+        assert!(parse_abs_axis("foo").is_err());
+    }
+
+    #[test]
+    fn test_threshold_crossing() {
+        let device = stick();
+        let mut thresholds = AbsThresholds::new();
+        thresholds.insert(*ABS_X, -50.0, *KEY_A);
+        thresholds.insert(*ABS_X, 50.0, *KEY_D);
+
+        assert_eq!(thresholds.handle_event(&ev(*ABS_X, 127), &device), vec![]);
+        assert_eq!(thresholds.handle_event(&ev(*ABS_X, 40), &device), vec![
+            ev(*KEY_A, TYPE_DOWN),
+            SYN_REPORT.clone(),
+        ]);
+        assert_eq!(thresholds.handle_event(&ev(*ABS_X, 0), &device), vec![]);
+        assert_eq!(thresholds.handle_event(&ev(*ABS_X, 220), &device), vec![
+            ev(*KEY_A, TYPE_UP),
+            SYN_REPORT.clone(),
+            ev(*KEY_D, TYPE_DOWN),
+            SYN_REPORT.clone(),
+        ]);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let device = stick();
+        let mut thresholds = AbsThresholds::new();
+        thresholds.insert(*ABS_X, 50.0, *KEY_D);
+
+        assert_eq!(thresholds.handle_event(&ev(*ABS_Y, 10), &device), vec![ev(*ABS_Y, 10)]);
+        assert_eq!(thresholds.handle_event(&ev(*KEY_A, TYPE_DOWN), &device), vec![ev(*KEY_A, TYPE_DOWN)]);
+    }
+}
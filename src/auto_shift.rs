@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+
+use crate::*;
+
+/// The events an input event turned into, along with a timer to start.
+#[derive(Debug, PartialEq)]
+pub struct AutoShiftOutput {
+    pub events: Vec<InputEvent>,
+    pub timer_id: Option<usize>,
+}
+
+impl AutoShiftOutput {
+    fn events(events: Vec<InputEvent>) -> Self { AutoShiftOutput { events, timer_id: None } }
+}
+
+/// Types the shifted variant of a key if it's held longer than the timeout.
+///
+/// A pressed key is held back until it's either released, which types it unshifted, or the timeout passes,
+/// which presses it along with shift. Pressing another key in the meantime types the held back key unshifted.
+/// Auto-repeat events are dropped while a key is held back, afterwards they repeat the shifted key. Shift stays
+/// down while any key is held shifted, keys typed unshifted in the meantime release it around them.
+pub struct AutoShift {
+    active: bool,
+    timeout: u64,
+    excluded: HashSet<Key>,
+    pending: Option<(Key, Arc<DeviceInfo>, usize)>,
+    // keys that were pressed along with shift and are still held
+    shifted: HashSet<Key>,
+    // the shift keys held on the input devices, the shift pressed along with the shifted keys isn't among them
+    held_shift: HashSet<Key>,
+    next_timer_id: usize,
+}
+
+impl AutoShift {
+    pub fn new() -> Self {
+        AutoShift {
+            active: false,
+            timeout: 175,
+            excluded: Default::default(),
+            pending: None,
+            shifted: Default::default(),
+            held_shift: Default::default(),
+            next_timer_id: 0,
+        }
+    }
+
+    pub fn set_active(&mut self, active: bool) { self.active = active; }
+
+    pub fn timeout(&self) -> u64 { self.timeout }
+
+    pub fn set_timeout(&mut self, timeout: u64) { self.timeout = timeout; }
+
+    pub fn exclude(&mut self, keys: Vec<Key>) { self.excluded.extend(keys); }
+
+    /// Whether shift is held, the shift pressed along with the shifted keys only counts if it was held anyway.
+    pub fn is_shift(&self, modifiers: &KeyModifierState) -> bool {
+        if self.shifted.is_empty() { modifiers.is_shift() } else { !self.held_shift.is_empty() }
+    }
+
+    /// Handles an input event, key presses with `bypass` set are never held back (i.e. if modifiers are held).
+    pub fn handle_event(&mut self, device: &Arc<DeviceInfo>, ev: &InputEvent, bypass: bool) -> AutoShiftOutput {
+        let key = match ev.event_code {
+            EventCode::EV_KEY(_) => Key { event_code: ev.event_code },
+            _ => return AutoShiftOutput::events(vec![ev.clone()]),
+        };
+
+        if key == *KEY_LEFT_SHIFT || key == *KEY_RIGHT_SHIFT {
+            if ev.value == TYPE_DOWN { self.held_shift.insert(key); }
+            if ev.value == TYPE_UP { self.held_shift.remove(&key); }
+        }
+
+        if self.shifted.contains(&key) && ev.value == TYPE_UP {
+            self.shifted.remove(&key);
+            let mut events = vec![ev.clone()];
+            // shift is shared by all shifted keys, it's released along with the last one
+            if self.shifted.is_empty() {
+                events.push(SYN_REPORT.clone());
+                events.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP).to_input_ev());
+            }
+            return AutoShiftOutput::events(events);
+        }
+
+        let mut events = vec![];
+        if let Some((pending_key, _, _)) = self.pending {
+            if pending_key == key {
+                if ev.value != TYPE_UP { return AutoShiftOutput::events(vec![]); }
+
+                // released before the timeout, tap the key unshifted
+                self.pending = None;
+                return AutoShiftOutput::events(self.unshifted(vec![
+                    KeyAction::new(key, TYPE_DOWN).to_input_ev(),
+                    SYN_REPORT.clone(),
+                    ev.clone(),
+                ]));
+            }
+
+            if ev.value != TYPE_DOWN { return AutoShiftOutput::events(vec![ev.clone()]); }
+
+            // pressing another key interrupts the held back key
+            self.pending = None;
+            events.extend(self.unshifted(vec![KeyAction::new(pending_key, TYPE_DOWN).to_input_ev()]));
+            events.push(SYN_REPORT.clone());
+        }
+
+        if ev.value == TYPE_DOWN && self.active && !bypass && is_auto_shift_key(&key) && !self.excluded.contains(&key) {
+            let timer_id = self.next_timer_id;
+            self.next_timer_id += 1;
+            self.pending = Some((key, device.clone(), timer_id));
+            return AutoShiftOutput { events, timer_id: Some(timer_id) };
+        }
+
+        events.push(ev.clone());
+        AutoShiftOutput::events(events)
+    }
+
+    /// Presses the held back key along with shift if it's still waiting on the given timer.
+    pub fn handle_timeout(&mut self, timer_id: usize) -> Option<(Arc<DeviceInfo>, Vec<InputEvent>)> {
+        let (key, device) = match self.pending.take() {
+            Some((key, device, pending_timer_id)) if pending_timer_id == timer_id => (key, device),
+            pending => {
+                self.pending = pending;
+                return None;
+            }
+        };
+
+        let mut events = vec![];
+        if self.shifted.is_empty() {
+            events.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN).to_input_ev());
+            events.push(SYN_REPORT.clone());
+        }
+        self.shifted.insert(key);
+        events.push(KeyAction::new(key, TYPE_DOWN).to_input_ev());
+        events.push(SYN_REPORT.clone());
+        Some((device, events))
+    }
+
+    /// Releases shift around events that type keys unshifted while another key is held shifted.
+    fn unshifted(&self, events: Vec<InputEvent>) -> Vec<InputEvent> {
+        if self.shifted.is_empty() { return events; }
+
+        let mut unshifted = vec![KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP).to_input_ev(), SYN_REPORT.clone()];
+        unshifted.extend(events);
+        unshifted.push(SYN_REPORT.clone());
+        unshifted.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN).to_input_ev());
+        unshifted
+    }
+}
+
+/// Alphanumeric and symbol keys, which have a shifted variant.
+pub fn is_auto_shift_key(key: &Key) -> bool {
+    matches!(key.char(), Some(c) if c.is_ascii_alphanumeric())
+        || ["KEY_MINUS", "KEY_EQUAL", "KEY_LEFTBRACE", "KEY_RIGHTBRACE", "KEY_SEMICOLON", "KEY_APOSTROPHE",
+            "KEY_GRAVE", "KEY_BACKSLASH", "KEY_COMMA", "KEY_DOT", "KEY_SLASH"].contains(&&*key.event_code.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn auto_shift() -> AutoShift {
+        let mut auto_shift = AutoShift::new();
+        auto_shift.set_active(true);
+        auto_shift
+    }
+
+    #[test]
+    fn test_tap() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false).timer_id, Some(0));
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_REPEAT), false).events, vec![]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_UP), false).events, vec![
+            ev(*KEY_A, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*KEY_A, TYPE_UP),
+        ]);
+        assert!(auto_shift.handle_timeout(0).is_none());
+    }
+
+    #[test]
+    fn test_hold() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false);
+        assert_eq!(auto_shift.handle_timeout(0).unwrap().1, vec![
+            ev(*KEY_LEFT_SHIFT, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*KEY_A, TYPE_DOWN),
+            SYN_REPORT.clone(),
+        ]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_REPEAT), false).events, vec![ev(*KEY_A, TYPE_REPEAT)]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_UP), false).events, vec![
+            ev(*KEY_A, TYPE_UP),
+            SYN_REPORT.clone(),
+            ev(*KEY_LEFT_SHIFT, TYPE_UP),
+        ]);
+    }
+
+    #[test]
+    fn test_overlapping_keys() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false);
+        auto_shift.handle_timeout(0);
+
+        // a quick tap while another key is held shifted is typed unshifted
+        auto_shift.handle_event(&device, &ev(*KEY_B, TYPE_DOWN), false);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_B, TYPE_UP), false).events, vec![
+            ev(*KEY_LEFT_SHIFT, TYPE_UP),
+            SYN_REPORT.clone(),
+            ev(*KEY_B, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*KEY_B, TYPE_UP),
+            SYN_REPORT.clone(),
+            ev(*KEY_LEFT_SHIFT, TYPE_DOWN),
+        ]);
+
+        // shift is pressed once and released along with the last shifted key
+        auto_shift.handle_event(&device, &ev(*KEY_C, TYPE_DOWN), false);
+        assert_eq!(auto_shift.handle_timeout(2).unwrap().1, vec![ev(*KEY_C, TYPE_DOWN), SYN_REPORT.clone()]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_UP), false).events, vec![ev(*KEY_A, TYPE_UP)]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_C, TYPE_REPEAT), false).events, vec![ev(*KEY_C, TYPE_REPEAT)]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_C, TYPE_UP), false).events, vec![
+            ev(*KEY_C, TYPE_UP),
+            SYN_REPORT.clone(),
+            ev(*KEY_LEFT_SHIFT, TYPE_UP),
+        ]);
+    }
+
+    #[test]
+    fn test_held_shift() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        let mut modifiers = KeyModifierState::new();
+        modifiers.left_shift = true;
+        assert!(auto_shift.is_shift(&modifiers));
+
+        // the shift pressed along with a shifted key isn't held
+        auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false);
+        auto_shift.handle_timeout(0);
+        assert!(!auto_shift.is_shift(&modifiers));
+
+        auto_shift.handle_event(&device, &ev(*KEY_RIGHT_SHIFT, TYPE_DOWN), false);
+        assert!(auto_shift.is_shift(&modifiers));
+        auto_shift.handle_event(&device, &ev(*KEY_RIGHT_SHIFT, TYPE_UP), false);
+        assert!(!auto_shift.is_shift(&modifiers));
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false);
+        let output = auto_shift.handle_event(&device, &ev(*KEY_B, TYPE_DOWN), false);
+        assert_eq!(output.events, vec![ev(*KEY_A, TYPE_DOWN), SYN_REPORT.clone()]);
+        assert_eq!(output.timer_id, Some(1));
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_UP), false).events, vec![ev(*KEY_A, TYPE_UP)]);
+        assert!(auto_shift.handle_timeout(0).is_none());
+    }
+
+    #[test]
+    fn test_excluded_keys() {
+        let device = keyboard();
+        let mut auto_shift = auto_shift();
+        auto_shift.exclude(vec![*KEY_A]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_A, TYPE_DOWN), false).events, vec![ev(*KEY_A, TYPE_DOWN)]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_B, TYPE_DOWN), true).events, vec![ev(*KEY_B, TYPE_DOWN)]);
+        assert_eq!(auto_shift.handle_event(&device, &ev(*KEY_ENTER, TYPE_DOWN), false).events, vec![ev(*KEY_ENTER, TYPE_DOWN)]);
+        assert!(is_auto_shift_key(&*KEY_SLASH));
+    }
+}
@@ -11,6 +11,8 @@ pub struct Configuration {
     pub script_file: fs::File,
     pub verbosity: i32,
     pub devices: Vec<String>,
+    // create the virtual gamepad even if no game controller is connected on startup
+    pub gamepad: bool,
 }
 
 pub fn parse_cli() -> Result<Configuration> {
@@ -29,6 +31,10 @@ pub fn parse_cli() -> Result<Configuration> {
             .long("--devices")
             .takes_value(true)
         )
+        .arg(Arg::with_name("gamepad")
+            .help("Creates a virtual gamepad, even if no game controller is connected")
+            .long("--gamepad")
+        )
         .arg(Arg::with_name("script file")
             .help("Executes the given script file")
             .index(1)
@@ -73,6 +79,7 @@ pub fn parse_cli() -> Result<Configuration> {
         script_file,
         verbosity,
         devices: device_list,
+        gamepad: matches.is_present("gamepad"),
     };
 
     Ok(config)
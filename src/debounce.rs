@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use crate::*;
+
+/// Drops key presses that follow a release of the same key too closely, which worn keyboards tend to
+/// register when a key is pressed once.
+///
+/// The thresholds are configured per key and scoped by conditions just like mappings, so they can differ
+/// between devices. A suppressed press suppresses the matching release as well.
+pub struct Debounce {
+    // the threshold for a key, or all keys if none is given
+    rules: Vec<(KeyActionCondition, Option<Key>, time::Duration)>,
+    last_release: HashMap<(String, Key), time::Instant>,
+    suppressed: HashSet<(String, Key)>,
+    suppressed_count: usize,
+}
+
+impl Debounce {
+    pub fn new() -> Self {
+        Debounce {
+            rules: vec![],
+            last_release: Default::default(),
+            suppressed: Default::default(),
+            suppressed_count: 0,
+        }
+    }
+
+    /// Sets the threshold for the given keys, or all keys if none are given. A threshold of 0 disables
+    /// debouncing.
+    pub fn set_threshold(&mut self, condition: KeyActionCondition, keys: Vec<Key>, threshold: time::Duration) {
+        let keys = if keys.is_empty() { vec![None] } else { keys.into_iter().map(Some).collect() };
+        for key in keys {
+            self.rules.retain(|(rule_condition, rule_key, _)| !(*rule_condition == condition && *rule_key == key));
+            self.rules.push((condition.clone(), key, threshold));
+        }
+    }
+
+    /// The number of events that were suppressed so far.
+    pub fn suppressed_count(&self) -> usize { self.suppressed_count }
+
+    // rules for single keys take precedence, followed by the rules with the most specific condition
+    fn threshold(&self, key: &Key, window: Option<&ActiveWindowInfo>, device: &DeviceInfo) -> Option<time::Duration> {
+        self.rules.iter()
+            .filter(|(condition, rule_key, _)| {
+                rule_key.map_or(true, |rule_key| rule_key == *key) && condition.is_satisfied(window, Some(device))
+            })
+            .max_by_key(|(condition, rule_key, _)| (rule_key.is_some(), condition.specificity()))
+            .map(|(_, _, threshold)| *threshold)
+    }
+
+    /// Returns false if the event should be dropped.
+    pub fn handle_event(&mut self, ev: &InputEvent, device: &DeviceInfo, window: Option<&ActiveWindowInfo>) -> bool {
+        if self.rules.is_empty() { return true; }
+
+        let key = match ev.event_code {
+            EventCode::EV_KEY(_) => Key { event_code: ev.event_code },
+            _ => return true,
+        };
+        let id = (device.path.clone(), key);
+
+        if self.suppressed.contains(&id) {
+            if ev.value == TYPE_UP { self.suppressed.remove(&id); }
+            self.suppressed_count += 1;
+            return false;
+        }
+
+        let now = time::Instant::now();
+        if ev.value == TYPE_UP {
+            self.last_release.insert(id, now);
+        } else if ev.value == TYPE_DOWN {
+            let threshold = self.threshold(&key, window, device).unwrap_or_default();
+            let chattering = self.last_release.get(&id)
+                .map_or(false, |last_release| now.duration_since(*last_release) < threshold);
+
+            if chattering {
+                self.suppressed.insert(id);
+                self.suppressed_count += 1;
+                return false;
+            }
+        }
+        true
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    #[test]
+    fn test_chattering_press() {
+        let old_keyboard = device("/dev/input/event1", "Old Keyboard");
+        let mut debounce = Debounce::new();
+        debounce.set_threshold(KeyActionCondition::new(), vec![], time::Duration::from_secs(60));
+
+        assert!(debounce.handle_event(&ev(*KEY_A, TYPE_DOWN), &old_keyboard, None));
+        assert!(debounce.handle_event(&ev(*KEY_A, TYPE_UP), &old_keyboard, None));
+        assert!(!debounce.handle_event(&ev(*KEY_A, TYPE_DOWN), &old_keyboard, None));
+        assert!(!debounce.handle_event(&ev(*KEY_A, TYPE_REPEAT), &old_keyboard, None));
+        assert!(!debounce.handle_event(&ev(*KEY_A, TYPE_UP), &old_keyboard, None));
+        assert!(debounce.handle_event(&ev(*KEY_B, TYPE_DOWN), &old_keyboard, None));
+        assert_eq!(debounce.suppressed_count(), 3);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let old_keyboard = device("/dev/input/event1", "Old Keyboard");
+        let new_keyboard = device("/dev/input/event2", "New Keyboard");
+
+        let mut condition = KeyActionCondition::new();
+        condition.set(ConditionProperty::DeviceName, ConditionPattern::new("Old Keyboard").unwrap());
+
+        let mut debounce = Debounce::new();
+        debounce.set_threshold(condition.clone(), vec![], time::Duration::from_secs(60));
+        debounce.set_threshold(condition, vec![*KEY_B], time::Duration::from_secs(0));
+
+        assert_eq!(debounce.threshold(&*KEY_A, None, &old_keyboard), Some(time::Duration::from_secs(60)));
+        assert_eq!(debounce.threshold(&*KEY_B, None, &old_keyboard), Some(time::Duration::from_secs(0)));
+        assert_eq!(debounce.threshold(&*KEY_A, None, &new_keyboard), None);
+    }
+}
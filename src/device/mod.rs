@@ -1,4 +1,4 @@
 pub mod virtual_input_device;
 mod virt_device;
 pub mod device_logging;
-mod virtual_output_device;
+pub mod virtual_output_device;
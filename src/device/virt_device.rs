@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
 use evdev_rs::*;
 use evdev_rs::Device;
@@ -17,6 +19,41 @@ fn set_code_bits(dev: &Device, ev_code: &EventCode, max: &EventCode) -> Result<(
     Ok(())
 }
 
+/// The absolute axes of a device along with their ranges.
+pub(crate) fn abs_axes(dev: &Device) -> Vec<(EventCode, AbsInfo)> {
+    let mut axes = vec![];
+    for code in EventCode::EV_ABS(EV_ABS::ABS_X).iter() {
+        if code == EventCode::EV_ABS(EV_ABS::ABS_MAX) { break; }
+        if !dev.has(&code) { continue; }
+        if let Some(info) = dev.abs_info(&code) {
+            axes.push((code, info));
+        }
+    }
+    axes
+}
+
+/// Every event code a device reports.
+pub(crate) fn event_codes(dev: &Device) -> HashSet<EventCode> {
+    EventCode::EV_SYN(EV_SYN::SYN_REPORT).iter()
+        .filter(|code| dev.has(code))
+        .collect()
+}
+
+/// Whether a device is a game controller.
+pub(crate) fn is_gamepad(dev: &Device) -> bool {
+    dev.has(&EventCode::EV_KEY(EV_KEY::BTN_SOUTH))
+}
+
+fn set_abs_bits(dev: &Device, axes: &[(EventCode, AbsInfo)]) -> Result<()> {
+    for (code, info) in axes.iter() {
+        // pass the raw struct, libevdev copies it while enabling the code
+        let raw_info = info.as_raw();
+        dev.enable_event_code(code, Some(&raw_info))
+            .map_err(|err| anyhow!("failed to enable abs axis {}: {}", code, err))?;
+    }
+    Ok(())
+}
+
 fn set_bits(dev: &Device) -> Result<()> {
     for ev_type in EventType::EV_SYN.iter() {
         match ev_type {
@@ -30,17 +67,11 @@ fn set_bits(dev: &Device) -> Result<()> {
                 &EventCode::EV_REL(EV_REL::REL_X),
                 &EventCode::EV_REL(EV_REL::REL_MAX),
             )?,
-            // EventType::EV_ABS => clone_code_bits(
-            //     dev,
-            //     &EventCode::EV_ABS(EV_ABS::ABS_X),
-            //     &EventCode::EV_ABS(EV_ABS::ABS_MAX),
-            // )?,
-            // EventType::EV_LED => {}
-                // clone_code_bits(
-                // dev,
-                // &EventCode::EV_LED(EV_LED::LED_NUML),
-                // &EventCode::EV_LED(EV_LED::LED_MAX),
-            // )?,
+            EventType::EV_LED => set_code_bits(
+                dev,
+                &EventCode::EV_LED(EV_LED::LED_NUML),
+                &EventCode::EV_LED(EV_LED::LED_MAX),
+            )?,
             _ => (),
         }
     }
@@ -53,3 +84,23 @@ pub(crate) fn init_virtual_device(dev: &Device) -> Result<()> {
 
     Ok(())
 }
+
+pub(crate) fn init_virtual_gamepad(dev: &Device) -> Result<()> {
+    dev.set_name("Virtual Gamepad");
+
+    for button in GAMEPAD_BUTTONS.iter() {
+        dev.enable(&button.event_code)
+            .map_err(|err| anyhow!("failed to enable code bit: {}", err))?;
+    }
+
+    let axes = GAMEPAD_AXES.iter()
+        .map(|&(axis, (minimum, maximum))| {
+            // filter out noise on the sticks, the same values the xpad driver uses
+            let (fuzz, flat) = if (minimum, maximum) == GAMEPAD_STICK_RANGE { (16, 128) } else { (0, 0) };
+            (axis.event_code, AbsInfo { value: 0, minimum, maximum, fuzz, flat, resolution: 0 })
+        })
+        .collect::<Vec<_>>();
+    set_abs_bits(dev, &axes)?;
+
+    Ok(())
+}
@@ -2,17 +2,66 @@ use std::{fs, io, thread, time};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use evdev_rs::*;
+use evdev_rs::enums::{EV_LED, EventCode, EventType};
 use notify::{DebouncedEvent, Watcher};
 use regex::Regex;
 use tokio::sync::{mpsc, oneshot};
 use tokio::task;
 use walkdir::WalkDir;
 
+use crate::Key;
+use crate::messaging::{ExecutionMessage, ExecutionMessageSender};
+
+use super::virtual_output_device::OutputEvent;
+
 use super::*;
 
+/// Identifies the physical device an input event originated from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+    pub path: String,
+    pub name: String,
+    pub vendor: u16,
+    pub product: u16,
+    /// The (min, max) range of every absolute axis the device reports.
+    pub abs_ranges: Vec<(EventCode, i32, i32)>,
+    /// Whether the device is a game controller, its events are sent through the virtual gamepad.
+    pub gamepad: bool,
+}
+
+impl DeviceInfo {
+    fn from_device(path: &Path, device: &Device) -> Self {
+        DeviceInfo {
+            path: path.to_string_lossy().to_string(),
+            name: device.name().unwrap_or("").to_string(),
+            vendor: device.vendor_id(),
+            product: device.product_id(),
+            abs_ranges: virt_device::abs_axes(device).iter()
+                .map(|(code, info)| (*code, info.minimum, info.maximum))
+                .collect(),
+            gamepad: virt_device::is_gamepad(device),
+        }
+    }
+
+    /// The vendor and product id formatted as 'vvvv:pppp' (hex), the same format `lsusb` uses.
+    pub fn id(&self) -> String {
+        format!("{:04x}:{:04x}", self.vendor, self.product)
+    }
+
+    /// The (min, max) range of an absolute axis, if the device reports it.
+    pub fn abs_range(&self, code: &EventCode) -> Option<(i32, i32)> {
+        self.abs_ranges.iter()
+            .find(|(axis, _, _)| axis == code)
+            .map(|(_, min, max)| (*min, *max))
+    }
+}
+
+pub type DeviceInputEvent = (Arc<DeviceInfo>, InputEvent);
+
 fn get_fd_list(patterns: &Vec<Regex>) -> Vec<PathBuf> {
     let mut list = vec![];
     for entry in WalkDir::new("/dev/input")
@@ -80,8 +129,11 @@ pub fn read_from_device_input_fd_thread_handler(
 
 
 async fn runner_it(fd_path: &Path,
-                   writer: mpsc::Sender<InputEvent>)
-                   -> Result<oneshot::Sender<()>> {
+                   writer: mpsc::Sender<DeviceInputEvent>,
+                   leds: &virtual_output_device::SharedLedOutputs,
+                   mirrors: &virtual_output_device::SharedMirrorOutputs,
+                   message_tx: &ExecutionMessageSender)
+                   -> Result<(oneshot::Sender<()>, Arc<DeviceInfo>)> {
     let fd_file = fs::OpenOptions::new()
         .read(true)
         .open(&fd_path)
@@ -92,27 +144,57 @@ async fn runner_it(fd_path: &Path,
     device.grab(GrabMode::Grab)
         .map_err(|err| anyhow!("failed to grab device '{}': {}", fd_path.to_string_lossy(), err))?;
 
+    let device_info = Arc::new(DeviceInfo::from_device(fd_path, &device));
+
+    // the lock state starts out as shown by the LEDs of the keyboards, LEDs that were already set keep their value
+    for code in EventCode::EV_LED(EV_LED::LED_NUML).iter() {
+        if code == EventCode::EV_LED(EV_LED::LED_MAX) { break; }
+        if !device.has(&code) || leds.lock().unwrap().has_value(&code) { continue; }
+        if let Some(value) = device.event_value(&code) {
+            let _ = message_tx.send(ExecutionMessage::LockStateChanged(Key { event_code: code }, value != 0)).await;
+        }
+    }
+
+    // LEDs are set through a separate handle, since the grabbed one is read only and owned by the read thread
+    if device.has(&EventType::EV_LED) {
+        let led_device = fs::OpenOptions::new().read(true).write(true).open(&fd_path).ok()
+            .and_then(|file| Device::new_from_file(file).ok());
+        if let Some(led_device) = led_device {
+            leds.lock().unwrap().insert(fd_path.to_path_buf(), led_device);
+        }
+    }
+
+    // absolute axes are passed through a clone of the device, so they keep their ranges and properties
+    if device.has(&EventType::EV_ABS) && !device_info.gamepad {
+        if let Err(err) = mirrors.lock().unwrap().insert(fd_path.to_path_buf(), &device) {
+            eprintln!("{}", err);
+        }
+    }
+
     // spawn tasks for reading devices
     let (abort_tx, abort_rx) = oneshot::channel();
+    let info = device_info.clone();
     thread::spawn(move || {
         read_from_device_input_fd_thread_handler(
             device,
             |ev| {
                 let _ = futures::executor::block_on(
-                    writer.send(ev)
+                    writer.send((info.clone(), ev))
                 );
             },
             abort_rx,
         );
     });
 
-    Ok(abort_tx)
+    Ok((abort_tx, device_info))
 }
 
 async fn runner
 (device_fd_path_pattens: Vec<Regex>,
- reader_init: oneshot::Sender<mpsc::Sender<InputEvent>>,
- writer: mpsc::Sender<InputEvent>,
+ gamepad: bool,
+ message_tx: ExecutionMessageSender,
+ reader_init: oneshot::Sender<mpsc::Sender<OutputEvent>>,
+ writer: mpsc::Sender<DeviceInputEvent>,
 ) -> Result<()> {
     task::spawn(async move {
         let (fs_reader_tx, reader_rx) = mpsc::channel(128);
@@ -120,10 +202,6 @@ async fn runner
         // send the reader to the client
         reader_init.send(fs_reader_tx.clone()).unwrap();
 
-        virtual_output_device::init_virtual_output_device(reader_rx).await
-            .map_err(|err| anyhow!("uinput error: {}", err))
-            .unwrap();
-
         #[derive(Debug)]
         enum FsWatchEvent {
             ADD(PathBuf),
@@ -156,10 +234,13 @@ async fn runner
         });
 
         let mut device_map = HashMap::new();
+        let leds = virtual_output_device::SharedLedOutputs::default();
+        let mirrors = virtual_output_device::SharedMirrorOutputs::default();
+        let mut has_gamepad = gamepad;
 
         for device_fd_path in get_fd_list(&device_fd_path_pattens) {
-            let res = runner_it(&device_fd_path, writer.clone()).await;
-            let abort_tx = match res {
+            let res = runner_it(&device_fd_path, writer.clone(), &leds, &mirrors, &message_tx).await;
+            let (abort_tx, device_info) = match res {
                 Ok(v) => v,
                 Err(err) => {
                     eprintln!("{}", err);
@@ -167,9 +248,18 @@ async fn runner
                 }
             };
 
+            // game controllers are passed through the virtual gamepad
+            if device_info.gamepad { has_gamepad = true; }
             device_map.insert(device_fd_path, abort_tx);
         }
 
+        // the output devices are created after grabbing the initial devices, the gamepad is only needed if one
+        // was grabbed
+        virtual_output_device::init_virtual_output_device(reader_rx, has_gamepad, leds.clone(), mirrors.clone(),
+                                                          message_tx.clone()).await
+            .map_err(|err| anyhow!("uinput error: {}", err))
+            .unwrap();
+
         loop {
             let fs_event = fs_event_rx.recv().await.unwrap();
             match fs_event {
@@ -177,11 +267,18 @@ async fn runner
                     if !device_fd_path_pattens.iter().any(|regex| regex.is_match(path.to_str().unwrap())) {
                         continue;
                     }
+                    if mirrors.lock().unwrap().is_mirror(&path) { continue; }
 
-                    let abort_tx = runner_it(&path, writer.clone()).await?;
+                    let (abort_tx, device_info) = runner_it(&path, writer.clone(), &leds, &mirrors, &message_tx).await?;
+                    if device_info.gamepad && !has_gamepad {
+                        eprintln!("game controller '{}' was plugged in after startup, run with '--gamepad' to pass it through",
+                                  path.to_string_lossy());
+                    }
                     device_map.insert(path, abort_tx);
                 }
                 FsWatchEvent::REMOVE(path) => {
+                    leds.lock().unwrap().remove(&path);
+                    mirrors.lock().unwrap().remove(&path);
                     if let Some(abort_tx) = device_map.remove(&path) {
                         // this might return an error if the device read thread crashed for any reason, ignore it since it was logged already
                         let _ = abort_tx.send(());
@@ -197,14 +294,15 @@ async fn runner
 }
 
 
-pub async fn bind_udev_inputs(fd_patterns: &[impl AsRef<str>], reader_init_tx: oneshot::Sender<mpsc::Sender<InputEvent>>, writer_tx: mpsc::Sender<InputEvent>) -> Result<()> {
+pub async fn bind_udev_inputs(fd_patterns: &[impl AsRef<str>], gamepad: bool, message_tx: ExecutionMessageSender,
+                              reader_init_tx: oneshot::Sender<mpsc::Sender<OutputEvent>>, writer_tx: mpsc::Sender<DeviceInputEvent>) -> Result<()> {
     let fd_patterns_regex = fd_patterns.into_iter()
         .map(|v| Regex::new(v.as_ref()))
         .collect::<std::result::Result<_, _>>()
         .map_err(|err| anyhow!("failed to parse regex: {}", err))?;
 
     task::spawn(async move {
-        runner(fd_patterns_regex, reader_init_tx, writer_tx).await.unwrap();
+        runner(fd_patterns_regex, gamepad, message_tx, reader_init_tx, writer_tx).await.unwrap();
         Ok::<(), anyhow::Error>(())
     });
 
@@ -1,15 +1,165 @@
-use evdev_rs::{UInputDevice, UninitDevice};
+use std::collections::HashSet;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use evdev_rs::{Device, LedState, UInputDevice, UninitDevice};
+use evdev_rs::enums::EventCode;
 use crate::*;
+use crate::messaging::{ExecutionMessage, ExecutionMessageSender};
 use super::*;
 
-pub async fn init_virtual_output_device(
-    mut reader_rx: mpsc::Receiver<InputEvent>,
-) -> Result<()> {
-    let mut new_device = UninitDevice::new()
+/// The device an output event is written to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    /// the virtual keyboard and mouse, gamepad buttons are written to the virtual gamepad
+    Default,
+    /// the grabbed device the event was passed through from, its mirror if the mirror reports the event or the
+    /// virtual gamepad for the axes and reports of game controllers
+    Device(Arc<DeviceInfo>),
+    /// the virtual gamepad
+    Gamepad,
+}
+
+/// An event written to the virtual output devices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputEvent {
+    pub target: OutputTarget,
+    pub ev: InputEvent,
+}
+
+impl OutputEvent {
+    /// An event that is passed through from a grabbed device.
+    pub fn passthrough(device: &Arc<DeviceInfo>, ev: InputEvent) -> Self {
+        OutputEvent { target: OutputTarget::Device(device.clone()), ev }
+    }
+}
+
+impl From<InputEvent> for OutputEvent {
+    fn from(ev: InputEvent) -> Self { OutputEvent { target: OutputTarget::Default, ev } }
+}
+
+/// The grabbed keyboards whose LEDs are set by the LED events written to the output.
+#[derive(Default)]
+pub(crate) struct LedOutputs {
+    devices: HashMap<PathBuf, Device>,
+    // the last value of each LED, applied to keyboards that are plugged in later
+    values: HashMap<EventCode, i32>,
+}
+
+pub(crate) type SharedLedOutputs = Arc<Mutex<LedOutputs>>;
+
+impl LedOutputs {
+    pub fn insert(&mut self, path: PathBuf, device: Device) {
+        for (code, value) in self.values.iter() {
+            let _ = set_led(&device, code, *value);
+        }
+        self.devices.insert(path, device);
+    }
+
+    pub fn remove(&mut self, path: &Path) { self.devices.remove(path); }
+
+    /// Whether an LED was set through the output.
+    pub fn has_value(&self, code: &EventCode) -> bool { self.values.contains_key(code) }
+
+    fn set(&mut self, code: EventCode, value: i32) {
+        self.values.insert(code, value);
+        for (path, device) in self.devices.iter() {
+            if let Err(err) = set_led(device, &code, value) {
+                eprintln!("failed to set LED of '{}': {}", path.to_string_lossy(), err);
+            }
+        }
+    }
+}
+
+/// The uinput clones of grabbed devices with absolute axes (i.e. touchpads and drawing tablets).
+///
+/// The events passed through from such a device are written to its clone, so it keeps its own axis ranges and
+/// properties, and is classified the same way as the grabbed device.
+#[derive(Default)]
+pub(crate) struct MirrorOutputs {
+    devices: HashMap<PathBuf, (UInputDevice, HashSet<EventCode>)>,
+}
+
+pub(crate) type SharedMirrorOutputs = Arc<Mutex<MirrorOutputs>>;
+
+impl MirrorOutputs {
+    pub fn insert(&mut self, path: PathBuf, source: &Device) -> Result<()> {
+        let device = UInputDevice::create_from_device(source)
+            .map_err(|err| anyhow!("failed to mirror device '{}': {}", path.to_string_lossy(), err))?;
+        self.devices.insert(path, (device, virt_device::event_codes(source)));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, path: &Path) { self.devices.remove(path); }
+
+    /// Whether a device node belongs to a mirror, mirrors must not be grabbed themselves.
+    pub fn is_mirror(&self, path: &Path) -> bool {
+        self.devices.values().any(|(device, _)| device.devnode().map(Path::new) == Some(path))
+    }
+
+    fn get(&self, path: &Path) -> Option<&UInputDevice> {
+        self.devices.get(path).map(|(device, _)| device)
+    }
+
+    fn reports(&self, path: &Path, code: &EventCode) -> bool {
+        self.devices.get(path).map_or(false, |(_, codes)| codes.contains(code))
+    }
+}
+
+/// The uinput device an output event is routed to.
+#[derive(Clone, PartialEq)]
+enum Output {
+    Virtual,
+    Gamepad,
+    Mirror(PathBuf),
+}
+
+fn route(target: &OutputTarget, ev: &InputEvent, mirrors: &MirrorOutputs, gamepad: bool) -> Output {
+    let is_key = match ev.event_code {
+        EventCode::EV_KEY(_) => true,
+        _ => false,
+    };
+    match target {
+        OutputTarget::Device(device) if mirrors.reports(Path::new(&device.path), &ev.event_code) =>
+            Output::Mirror(PathBuf::from(&device.path)),
+        OutputTarget::Device(device) if gamepad && device.gamepad && !is_key => Output::Gamepad,
+        OutputTarget::Gamepad if gamepad => Output::Gamepad,
+        _ if gamepad && is_gamepad_button(ev) => Output::Gamepad,
+        _ => Output::Virtual,
+    }
+}
+
+fn set_led(device: &Device, code: &EventCode, value: i32) -> io::Result<()> {
+    device.kernel_set_led_value(code, if value != 0 { LedState::On } else { LedState::Off })
+}
+
+/// Reads the LED events the system sends to the virtual output device, which reflect the lock state.
+fn read_lock_state_thread_handler(fd: RawFd, message_tx: ExecutionMessageSender) {
+    let mut raw_ev: libc::input_event = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::input_event>();
+    loop {
+        let read = unsafe { libc::read(fd, &mut raw_ev as *mut _ as *mut libc::c_void, size) };
+        if read != size as isize {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted { continue; }
+            return;
+        }
+
+        let ev = InputEvent::from_raw(&raw_ev);
+        if let EventCode::EV_LED(_) = ev.event_code {
+            let msg = ExecutionMessage::LockStateChanged(Key { event_code: ev.event_code }, ev.value != 0);
+            if futures::executor::block_on(message_tx.send(msg)).is_err() { return; }
+        }
+    }
+}
+
+fn create_uinput_device(init: impl FnOnce(&Device) -> Result<()>) -> Result<UInputDevice> {
+    let new_device = UninitDevice::new()
         .ok_or(anyhow!("failed to instantiate udev device: libevdev didn't return a device"))?
         .unstable_force_init();
 
-    virt_device::init_virtual_device(&mut new_device)
+    init(&new_device)
         .map_err(|err| anyhow!("failed to instantiate udev device: {}", err))?;
 
     let input_device = UInputDevice::create_from_device(&new_device);
@@ -20,17 +170,64 @@ pub async fn init_virtual_output_device(
         }
     };
 
-    let input_device = input_device.map_err(|err| anyhow!("failed to initialize uinput device: {}", err))?;
+    input_device.map_err(|err| anyhow!("failed to initialize uinput device: {}", err))
+}
+
+pub(crate) async fn init_virtual_output_device(
+    mut reader_rx: mpsc::Receiver<OutputEvent>,
+    gamepad: bool,
+    leds: SharedLedOutputs,
+    mirrors: SharedMirrorOutputs,
+    message_tx: ExecutionMessageSender,
+) -> Result<()> {
+    let input_device = create_uinput_device(|dev| virt_device::init_virtual_device(dev))?;
+    if let Some(fd) = input_device.as_fd() {
+        thread::spawn(move || read_lock_state_thread_handler(fd, message_tx));
+    }
+    let gamepad_device = match gamepad {
+        true => Some(create_uinput_device(|dev| virt_device::init_virtual_gamepad(dev))?),
+        false => None,
+    };
 
     task::spawn(async move {
+        // the devices that received events since the last SYN_REPORT
+        let mut pending: Vec<Output> = vec![];
         loop {
             let msg = reader_rx.recv().await;
-            let ev: InputEvent = match msg {
+            let OutputEvent { target, ev } = match msg {
                 Some(v) => v,
                 None => return Err(anyhow!("message channel closed unexpectedly")),
             };
-            input_device.write_event(&ev)
-                .map_err(|err| anyhow!("failed to write event into uinput device: {}", err))?;
+
+            // LED events set the LEDs of the grabbed keyboards
+            if let EventCode::EV_LED(_) = ev.event_code {
+                leds.lock().unwrap().set(ev.event_code, ev.value);
+                continue;
+            }
+
+            let mirrors = mirrors.lock().unwrap();
+            let output = route(&target, &ev, &mirrors, gamepad_device.is_some());
+            let outputs = if ev.event_code == SYN_REPORT.event_code && !pending.is_empty() {
+                std::mem::take(&mut pending)
+            } else {
+                if ev.event_code != SYN_REPORT.event_code && !pending.contains(&output) { pending.push(output.clone()); }
+                vec![output]
+            };
+
+            for output in outputs {
+                let device = match &output {
+                    Output::Virtual => Some(&input_device),
+                    Output::Gamepad => gamepad_device.as_ref(),
+                    Output::Mirror(path) => mirrors.get(path),
+                };
+                // the mirror is gone if its device was unplugged in the meantime
+                let device = match device {
+                    Some(device) => device,
+                    None => continue,
+                };
+                device.write_event(&ev)
+                    .map_err(|err| anyhow!("failed to write event into uinput device: {}", err))?;
+            }
         }
         #[allow(unreachable_code)]
             Ok(())
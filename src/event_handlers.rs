@@ -2,9 +2,15 @@ use crate::*;
 use messaging::*;
 use crate::cli::Configuration;
 
-fn update_modifiers(state: &mut State, ev: &InputEvent) {
+/// Tracks the held modifiers, returns false if the event should be swallowed since a sticky modifier is kept held.
+fn update_modifiers(state: &mut State, ev: &InputEvent) -> bool {
     // let ignore_list = &mut state.ignore_list;
 
+    let key = Key { event_code: ev.event_code };
+    if key.is_modifier() && !state.sticky_modifiers.handle_modifier_event(key, ev.value) {
+        return false;
+    }
+
     // TODO find a way to do this with a single accessor function
     let pairs: [(Key, fn(&KeyModifierState) -> bool, fn(&mut KeyModifierState) -> &mut bool); 8] = [
         (*KEY_LEFT_CTRL, |s| s.left_ctrl, |s: &mut KeyModifierState| &mut s.left_ctrl),
@@ -22,12 +28,12 @@ fn update_modifiers(state: &mut State, ev: &InputEvent) {
             let mut new_modifiers = state.modifiers.deref().clone();
             *modifier_mut(&mut new_modifiers) = true;
             state.modifiers = Arc::new(new_modifiers);
-            return;
+            return true;
         } else if ev.event_code == key.event_code && ev.value == TYPE_UP {
             let mut new_modifiers = state.modifiers.deref().clone();
             *modifier_mut(&mut new_modifiers) = false;
             state.modifiers = Arc::new(new_modifiers);
-            return;
+            return true;
             // TODO re-implement eating or throw it out completely
             // if ignore_list.is_ignored(&KeyAction::new(*key, TYPE_UP)) {
             //     ignore_list.unignore(&KeyAction::new(*key, TYPE_UP));
@@ -35,13 +41,15 @@ fn update_modifiers(state: &mut State, ev: &InputEvent) {
             // }
         }
     };
+    true
 }
 
 pub async fn handle_stdin_ev(
-    mut state: &mut State,
+    state: &mut State,
     ev: InputEvent,
+    device: &Arc<DeviceInfo>,
     mappings: &mut CompiledKeyMappings,
-    ev_writer: &mut mpsc::Sender<InputEvent>,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
     message_tx: &mut ExecutionMessageSender,
     window_cycle_token: usize,
     configuration: &Configuration,
@@ -50,19 +58,353 @@ pub async fn handle_stdin_ev(
         logging::print_debug(format!("input event: {}", logging::print_input_event(&ev)));
     }
 
+    // keyboards report changes of their LEDs, the lock state is tracked through the virtual output device instead
+    if let EventCode::EV_LED(_) = ev.event_code { return Ok(()); }
+
+    if !state.debounce.handle_event(&ev, device, state.active_window.as_ref()) {
+        if configuration.verbosity >= 2 {
+            logging::print_debug(format!("suppressed chattering event: {}", logging::print_input_event(&ev)));
+        }
+        return Ok(());
+    }
+
+    // the pointer stages each turn an event into any number of events
+    let joystick_mouse = &mut state.joystick_mouse;
+    let events = state.abs_thresholds.handle_event(&ev, device).iter()
+        .flat_map(|ev| joystick_mouse.handle_event(ev, device))
+        .collect::<Vec<_>>();
+
+    // the gamepad events are written right away, they don't concern the following stages
+    let mut pointer_events = vec![];
+    for ev in events {
+        match state.gamepad.handle_event(&ev, device) {
+            Some(gamepad_events) => for ev in gamepad_events {
+                ev_writer.send(OutputEvent { target: OutputTarget::Gamepad, ev }).await.unwrap();
+            },
+            None => pointer_events.push(ev),
+        }
+    }
+
+    let pointer_transform = &mut state.pointer_transform;
+    let active_window = state.active_window.as_ref();
+    let events = pointer_events.iter()
+        .flat_map(|ev| pointer_transform.handle_event(ev, device, active_window))
+        .collect::<Vec<_>>();
+    let scroll_emulation = &mut state.scroll_emulation;
+    let events = events.iter().flat_map(|ev| scroll_emulation.handle_event(ev)).collect::<Vec<_>>();
+    state.joystick_mouse.update_ticker(message_tx);
+
+    for ev in events {
+        let output = state.gestures.handle_event(&ev);
+        if let Some(callback) = output.callback {
+            let trigger = Key { event_code: ev.event_code };
+            let execution_mode = callback.2.execution_mode;
+            let job = run_mapping(callback, ev_writer.clone(), message_tx.clone(), window_cycle_token,
+                                  state.modifiers.clone(), KeyAction::new(trigger, ev.value), device.clone(), None);
+            state.executor.execute(trigger, execution_mode, job);
+        }
+
+        for ev in output.events {
+            state.macros.record(&ev);
+            handle_input_ev(state, ev, device, mappings, ev_writer, message_tx, window_cycle_token, None).await?;
+        }
+    }
+
+    update_layer_leds(state, ev_writer).await;
+    Ok(())
+}
+
+/// Runs an input event through the input stages, events replayed from a tap dance buffer are queued behind the
+/// callback of the dance.
+#[async_recursion]
+async fn handle_input_ev(
+    state: &mut State,
+    ev: InputEvent,
+    device: &Arc<DeviceInfo>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+    queue: Option<Key>,
+) -> Result<()> {
+    match state.tap_dances.handle_event(device, &ev) {
+        TapDanceStep::Pass => {}
+        TapDanceStep::Swallow => return Ok(()),
+        TapDanceStep::StartTimer(timer_id) => {
+            send_delayed(message_tx, state.tap_dances.timeout(), ExecutionMessage::TapDanceTimeout(timer_id));
+            return Ok(());
+        }
+        TapDanceStep::Resolve => {
+            return resolve_tap_dance(state, mappings, ev_writer, message_tx, window_cycle_token).await;
+        }
+        TapDanceStep::Released => {
+            let key = Key { event_code: ev.event_code };
+            state.layers.handle_key_event(&KeyActionWithMods::new(key, TYPE_UP, KeyModifierFlags::new()));
+            return Ok(());
+        }
+    }
+
+    let key = Key { event_code: ev.event_code };
+    let bypass_auto_shift = state.modifiers.is_ctrl() || state.modifiers.is_alt() || state.auto_shift.is_shift(&state.modifiers)
+        || state.modifiers.is_meta() || state.custom_modifiers.active().is_some()
+        || state.custom_modifiers.is_declared(&key, &state.layers, state.active_window.as_ref(), Some(device));
+    let output = state.auto_shift.handle_event(device, &ev, bypass_auto_shift);
+    if let Some(timer_id) = output.timer_id {
+        send_delayed(message_tx, state.auto_shift.timeout(), ExecutionMessage::AutoShiftTimeout(timer_id));
+    }
+
+    for ev in output.events {
+        dispatch_input_ev(state, ev, device, mappings, ev_writer, message_tx, window_cycle_token, queue).await?;
+    }
+    Ok(())
+}
+
+/// Runs an input event through the stages following auto shift.
+async fn dispatch_input_ev(
+    state: &mut State,
+    ev: InputEvent,
+    device: &Arc<DeviceInfo>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+    queue: Option<Key>,
+) -> Result<()> {
     match ev.event_code {
         EventCode::EV_KEY(_) => {}
+        EventCode::EV_REL(_) => {
+            return handle_rel_ev(state, ev, device, mappings, ev_writer, message_tx, window_cycle_token, queue).await;
+        }
         _ => {
-            ev_writer.send(ev).await.unwrap();
+            write_events(state, ev_writer, queue, vec![OutputEvent::passthrough(device, ev)]).await;
             return Ok(());
         }
     }
 
-    let mut from_modifiers = KeyModifierFlags::new();
-    from_modifiers.ctrl = state.modifiers.is_ctrl();
-    from_modifiers.alt = state.modifiers.is_alt();
-    from_modifiers.shift = state.modifiers.is_shift();
-    from_modifiers.meta = state.modifiers.is_meta();
+    let queue = state.tap_dances.replay_queue(&ev, queue);
+
+    // a custom modifier is released as such even if its mappings don't apply anymore
+    let key = Key { event_code: ev.event_code };
+    let is_custom_modifier = if ev.value == TYPE_UP {
+        state.custom_modifiers.is_held(&key)
+    } else {
+        state.custom_modifiers.is_declared(&key, &state.layers, state.active_window.as_ref(), Some(device))
+    };
+    if is_custom_modifier {
+        if ev.value == TYPE_DOWN {
+            state.custom_modifiers.mark_used();
+            state.custom_modifiers.press(key);
+        } else if ev.value == TYPE_UP && state.custom_modifiers.release(&key) {
+            // tap the key if it wasn't combined with another key
+            handle_key_ev(state, KeyAction::new(key, TYPE_DOWN).to_input_ev(), device, mappings,
+                          ev_writer, message_tx, window_cycle_token, queue).await?;
+            write_events(state, ev_writer, queue, vec![SYN_REPORT.clone()]).await;
+            handle_key_ev(state, ev, device, mappings, ev_writer, message_tx, window_cycle_token, queue).await?;
+        }
+        return Ok(());
+    }
+
+    if ev.value == TYPE_DOWN { state.custom_modifiers.mark_used(); }
+
+    handle_key_ev(state, ev, device, mappings, ev_writer, message_tx, window_cycle_token, queue).await
+}
+
+/// Dispatches relative events (i.e. wheel or movement) to mappings of their direction or the whole axis,
+/// unmapped events are passed through.
+async fn handle_rel_ev(
+    state: &mut State,
+    ev: InputEvent,
+    device: &Arc<DeviceInfo>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+    queue: Option<Key>,
+) -> Result<()> {
+    let mut modifiers = state.modifiers.to_flags();
+    modifiers.custom = state.custom_modifiers.active();
+
+    // high resolution wheel events trigger the mappings of their wheel
+    let (key, hi_res) = match hi_res_wheel(Key { event_code: ev.event_code }) {
+        Some(wheel) => (wheel, true),
+        None => (Key { event_code: ev.event_code }, false),
+    };
+    let direction = if ev.value < 0 { REL_DIRECTION_NEGATIVE } else { REL_DIRECTION_POSITIVE };
+
+    let (layers, active_window) = (&state.layers, state.active_window.as_ref());
+    let mapping = [direction, REL_DIRECTION_ANY].iter()
+        .map(|value| KeyActionWithMods { key, value: *value, modifiers })
+        .find_map(|action| layers.lookup(&action, active_window, Some(device))
+            .or_else(|| mappings.get(&action, active_window, Some(device)))
+            .cloned());
+
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => {
+            write_events(state, ev_writer, queue, vec![OutputEvent::passthrough(device, ev)]).await;
+            return Ok(());
+        }
+    };
+
+    state.custom_modifiers.mark_used();
+
+    let value = if hi_res {
+        match state.wheel_notches.scroll(&device.path, key, ev.value) {
+            0 => return Ok(()),
+            notches => notches,
+        }
+    } else if state.wheel_notches.is_hi_res(&device.path, key) {
+        // the high resolution events of the same frame already triggered the mapping
+        return Ok(());
+    } else {
+        ev.value
+    };
+
+    let execution_mode = mapping.2.execution_mode;
+    let job = run_mapping(mapping, ev_writer.clone(), message_tx.clone(), window_cycle_token,
+                          state.modifiers.clone(), KeyAction::new(key, value), device.clone(), None);
+    match queue {
+        Some(queue) if execution_mode != ExecutionMode::Concurrent =>
+            state.executor.execute(queue, ExecutionMode::PerKey, job),
+        _ => state.executor.execute(key, execution_mode, job),
+    }
+    Ok(())
+}
+
+fn send_delayed(message_tx: &ExecutionMessageSender, delay: u64, msg: ExecutionMessage) {
+    let message_tx = message_tx.clone();
+    task::spawn(async move {
+        tokio::time::sleep(time::Duration::from_millis(delay)).await;
+        let _ = message_tx.send(msg).await;
+    });
+}
+
+/// Dispatches the callback of the pending tap dance, or the key itself if there's no callback, and replays the
+/// events buffered in the meantime.
+async fn resolve_tap_dance(
+    state: &mut State,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    let resolved = match state.tap_dances.resolve() {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+
+    if let Some(callback) = resolved.callback {
+        let job = run_mapping(callback, ev_writer.clone(), message_tx.clone(), window_cycle_token,
+                              state.modifiers.clone(), KeyAction::new(resolved.key, TYPE_DOWN), resolved.device.clone(),
+                              resolved.invocation);
+        state.executor.execute(resolved.key, ExecutionMode::PerKey, job);
+    }
+
+    for ev in resolved.replay {
+        dispatch_input_ev(state, ev, &resolved.device, mappings, ev_writer, message_tx, window_cycle_token,
+                          Some(resolved.key)).await?;
+    }
+
+    for (device, ev) in resolved.buffer {
+        handle_input_ev(state, ev, &device, mappings, ev_writer, message_tx, window_cycle_token,
+                        Some(resolved.key)).await?;
+    }
+    Ok(())
+}
+
+/// Writes events right away or, if a queue is given, once the invocations queued for its key finished.
+async fn write_events(state: &mut State, ev_writer: &mut mpsc::Sender<OutputEvent>, queue: Option<Key>,
+                      events: Vec<impl Into<OutputEvent> + Send + 'static>) {
+    match queue {
+        Some(queue) => {
+            let ev_writer = ev_writer.clone();
+            state.executor.execute(queue, ExecutionMode::PerKey, async move {
+                for ev in events { ev_writer.send(ev.into()).await.unwrap(); }
+            });
+        }
+        None => for ev in events { ev_writer.send(ev.into()).await.unwrap(); }
+    }
+}
+
+/// Evaluates a mapping, the callbacks registered with 'on_release' run once the triggering key is released.
+async fn run_mapping(
+    mapping: CompiledKeyMapping,
+    ev_writer: mpsc::Sender<OutputEvent>,
+    mut message_tx: ExecutionMessageSender,
+    window_cycle_token: usize,
+    modifier_state: Arc<KeyModifierState>,
+    trigger: KeyAction,
+    device: Arc<DeviceInfo>,
+    invocation: Option<KeyPressInvocation>,
+) {
+    let (block, var_map, _) = mapping.deref();
+    let mut amb = Ambient {
+        ev_writer_tx: ev_writer.clone(),
+        message_tx: Some(&mut message_tx),
+        window_cycle_token,
+        modifier_state: &modifier_state,
+        layer: None,
+        condition: KeyActionCondition::new(),
+        trigger_key: Some(trigger.key),
+        trigger_value: Some(trigger.value),
+        device: Some(device.clone()),
+        invocation: invocation.clone(),
+    };
+
+    let invocation = match invocation {
+        Some(invocation) => invocation,
+        None => {
+            eval_block(&block, &var_map, &mut amb).await;
+            return;
+        }
+    };
+
+    if invocation.cancels_on_release() {
+        // releasing the key cancels the mapping at the next suspension point (i.e. a pending sleep)
+        tokio::select! {
+            _ = eval_block(&block, &var_map, &mut amb) => {}
+            _ = invocation.released() => {}
+        }
+    } else {
+        eval_block(&block, &var_map, &mut amb).await;
+    }
+
+    let on_release = invocation.take_on_release();
+    if on_release.is_empty() { return; }
+
+    // wait for the release outside of the execution queue so later invocations aren't held up
+    task::spawn(async move {
+        invocation.released().await;
+        let mut amb = Ambient {
+            ev_writer_tx: ev_writer,
+            message_tx: Some(&mut message_tx),
+            window_cycle_token,
+            modifier_state: &modifier_state,
+            layer: None,
+            condition: KeyActionCondition::new(),
+            trigger_key: Some(trigger.key),
+            trigger_value: Some(trigger.value),
+            device: Some(device),
+            invocation: None,
+        };
+        for (block, var_map) in on_release {
+            eval_block(&block, &var_map, &mut amb).await;
+        }
+    });
+}
+
+async fn handle_key_ev(
+    mut state: &mut State,
+    ev: InputEvent,
+    device: &Arc<DeviceInfo>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+    queue: Option<Key>,
+) -> Result<()> {
+    let mut from_modifiers = state.modifiers.to_flags();
+    from_modifiers.custom = state.custom_modifiers.active();
 
     let from_key_action = KeyActionWithMods {
         key: Key { event_code: ev.event_code },
@@ -70,23 +412,95 @@ pub async fn handle_stdin_ev(
         modifiers: from_modifiers,
     };
 
-    update_modifiers(&mut state, &ev);
+    if !update_modifiers(&mut state, &ev) { return Ok(()); }
 
-    if let Some(block) = mappings.0.get(&from_key_action) {
-        let block = block.clone();
-        let mut message_tx = message_tx.clone();
-        let ev_writer = ev_writer.clone();
-        let modifier_state = state.modifiers.clone();
-        task::spawn(async move {
-            let (block, var_map) = block.deref();
-            let mut amb = Ambient { ev_writer_tx: ev_writer, message_tx: Some(&mut message_tx), window_cycle_token, modifier_state: &modifier_state };
+    let is_key_press = ev.value == TYPE_DOWN && !from_key_action.key.is_modifier();
+    let released_sticky_modifiers = if is_key_press { state.sticky_modifiers.handle_key_press() } else { vec![] };
+    if is_key_press && !is_caps_word_key(&from_key_action.key) { state.caps_word = false; }
 
-            eval_block(&block, &var_map, &mut amb).await;
-        });
-        return Ok(());
+    // active layers take precedence over the global mappings
+    let (layers, active_window) = (&state.layers, state.active_window.as_ref());
+    let lookup = |action: &KeyActionWithMods| layers.lookup(action, active_window, Some(device))
+        .or_else(|| mappings.get(action, active_window, Some(device)))
+        .cloned();
+
+    // the release and repeat events of a key are paired with its press, so they go to the same mapping even if
+    // the held modifiers change in between
+    let mapping = if from_key_action.value == TYPE_DOWN {
+        let (mapping, active_invocation) = layers.active_mappings()
+            .chain(Some(&*mappings))
+            .find_map(|mappings| mappings.get_invocation(&from_key_action, active_window, Some(device)))
+            .map(|(mapping, invocation)| (Some(mapping), invocation))
+            .unwrap_or_else(|| {
+                let with_value = |value| KeyActionWithMods { value, ..from_key_action };
+                (None, ActiveInvocation {
+                    up: lookup(&with_value(TYPE_UP)),
+                    repeat: lookup(&with_value(TYPE_REPEAT)),
+                    release_tx: None,
+                })
+            });
+
+        // the press mapping is notified once the key is released
+        let (release_tx, invocation) = match &mapping {
+            Some(mapping) => {
+                let (release_tx, press_invocation) = KeyPressInvocation::new(mapping.2.cancel_on_release);
+                (Some(release_tx), Some(press_invocation))
+            }
+            None => (None, None),
+        };
+        state.active_invocations.insert((device.path.clone(), from_key_action.key),
+                                        ActiveInvocation { release_tx, ..active_invocation });
+        mapping.map(|mapping| (mapping, invocation))
+    } else if from_key_action.value == TYPE_UP {
+        match state.active_invocations.remove(&(device.path.clone(), from_key_action.key)) {
+            Some(invocation) => {
+                if let Some(release_tx) = invocation.release_tx { let _ = release_tx.send(true); }
+                invocation.up.map(|mapping| (mapping, None))
+            }
+            None => lookup(&from_key_action).map(|mapping| (mapping, None)),
+        }
+    } else {
+        match state.active_invocations.get(&(device.path.clone(), from_key_action.key)) {
+            Some(invocation) => invocation.repeat.clone(),
+            None => lookup(&from_key_action),
+        }.map(|mapping| (mapping, None))
+    };
+
+    state.layers.handle_key_event(&from_key_action);
+
+    if let Some((mapping, invocation)) = mapping {
+        let execution_mode = mapping.2.execution_mode;
+        let job = run_mapping(mapping, ev_writer.clone(), message_tx.clone(), window_cycle_token,
+                              state.modifiers.clone(), KeyAction::new(from_key_action.key, from_key_action.value),
+                              device.clone(), invocation);
+        match queue {
+            Some(queue) if execution_mode != ExecutionMode::Concurrent =>
+                state.executor.execute(queue, ExecutionMode::PerKey, job),
+            _ => state.executor.execute(from_key_action.key, execution_mode, job),
+        }
+    } else if state.caps_word && ev.value != TYPE_UP && is_caps_word_shifted_key(&from_key_action.key)
+        && !state.modifiers.is_shift() {
+        write_events(state, ev_writer, queue, vec![
+            KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN).to_input_ev(),
+            SYN_REPORT.clone(),
+            ev,
+            SYN_REPORT.clone(),
+            KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP).to_input_ev(),
+        ]).await;
+    } else {
+        write_events(state, ev_writer, queue, vec![OutputEvent::passthrough(device, ev)]).await;
     }
 
-    ev_writer.send(ev).await.unwrap();
+    // latched sticky modifiers are released once the mappings of the key that used them are done
+    for key in released_sticky_modifiers {
+        update_modifiers(&mut state, &KeyAction::new(key, TYPE_UP).to_input_ev());
+
+        let ev_writer = ev_writer.clone();
+        state.executor.execute(queue.unwrap_or(from_key_action.key), ExecutionMode::PerKey, async move {
+            ev_writer.send(KeyAction::new(key, TYPE_UP).to_input_ev().into()).await.unwrap();
+            ev_writer.send(SYN_REPORT.clone().into()).await.unwrap();
+        });
+    }
 
     Ok(())
 }
@@ -98,15 +512,210 @@ pub async fn handle_execution_message(
     msg: ExecutionMessage,
     state: &mut State,
     mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<OutputEvent>,
+    message_tx: &mut ExecutionMessageSender,
     window_change_handlers: &mut Vec<(Block, GuardedVarMap)>,
 ) {
     match msg {
         // ExecutionMessage::EatEv(action) => {
         //     state.ignore_list.ignore(&action);
         // }
-        ExecutionMessage::AddMapping(token, from, to, var_map) => {
+        ExecutionMessage::AddMapping(token, layer, condition, from, to, var_map, options) => {
             if token == current_token {
-                mappings.0.insert(from, Arc::new((to, var_map)));
+                if let Some(key) = from.modifiers.custom {
+                    state.custom_modifiers.declare(key, layer.clone(), condition.clone());
+                }
+
+                let mappings = match layer {
+                    Some(layer) => state.layers.mappings_mut(&layer),
+                    None => mappings,
+                };
+                mappings.insert(from, condition, Arc::new((to, var_map, options)));
+            }
+        }
+        ExecutionMessage::ActivateLayer(name, activation) => {
+            // the mapping runs asynchronously, the key may have been released before the layer got activated
+            let released = match activation {
+                LayerActivation::WhileHeld(key) => !state.active_invocations.keys().any(|(_, held)| *held == key),
+                _ => false,
+            };
+            if !released { state.layers.activate(name, activation); }
+        }
+        ExecutionMessage::DeactivateLayer(name) => {
+            state.layers.deactivate(&name);
+        }
+        ExecutionMessage::ToggleLayer(name) => {
+            state.layers.toggle(name);
+        }
+        ExecutionMessage::GetActiveLayers(tx) => {
+            tx.send(state.layers.active_names()).await.unwrap();
+        }
+        ExecutionMessage::SetStickyModifiers(modifiers) => {
+            state.sticky_modifiers.set_enabled(modifiers);
+        }
+        ExecutionMessage::SetCapsWord(active) => {
+            state.caps_word = active;
+        }
+        ExecutionMessage::AddTapDance(key, action, block, var_map) => {
+            state.tap_dances.insert(key, action, Arc::new((block, var_map, MappingOptions::default())));
+        }
+        ExecutionMessage::SetTapDanceTimeout(timeout) => {
+            state.tap_dances.set_timeout(timeout);
+        }
+        ExecutionMessage::TapDanceTimeout(timer_id) => {
+            if state.tap_dances.is_timer_active(timer_id) {
+                resolve_tap_dance(state, mappings, ev_writer, message_tx, current_token).await.unwrap();
+            }
+        }
+        ExecutionMessage::SetAutoShift(active) => {
+            state.auto_shift.set_active(active);
+        }
+        ExecutionMessage::SetAutoShiftTimeout(timeout) => {
+            state.auto_shift.set_timeout(timeout);
+        }
+        ExecutionMessage::ExcludeFromAutoShift(keys) => {
+            state.auto_shift.exclude(keys);
+        }
+        ExecutionMessage::AutoShiftTimeout(timer_id) => {
+            if let Some((device, events)) = state.auto_shift.handle_timeout(timer_id) {
+                for ev in events {
+                    dispatch_input_ev(state, ev, &device, mappings, ev_writer, message_tx, current_token, None).await.unwrap();
+                }
+            }
+        }
+        ExecutionMessage::StartMacroRecording(name) => {
+            state.macros.start_recording(name);
+        }
+        ExecutionMessage::StopMacroRecording(trigger_key) => {
+            state.macros.stop_recording(trigger_key);
+        }
+        ExecutionMessage::GetMacro(name, tx) => {
+            tx.send(state.macros.get(&name)).await.unwrap();
+        }
+        ExecutionMessage::SetMacro(name, events) => {
+            state.macros.insert(name, events);
+        }
+        ExecutionMessage::PlayMacroEvent(device, ev) => {
+            // the events were recorded right before the input stages, the replay continues from there
+            handle_input_ev(state, ev, &device, mappings, ev_writer, message_tx, current_token, None).await.unwrap();
+            update_layer_leds(state, ev_writer).await;
+        }
+        ExecutionMessage::StartTurbo(id, actions, interval, jitter) => {
+            state.turbo.start(id, actions, interval, jitter, ev_writer.clone());
+        }
+        ExecutionMessage::ToggleTurbo(id, actions, interval, jitter) => {
+            if state.turbo.is_running(&id) {
+                state.turbo.stop(&id);
+            } else {
+                state.turbo.start(id, actions, interval, jitter, ev_writer.clone());
+            }
+        }
+        ExecutionMessage::StopTurbo(id) => {
+            match id {
+                Some(id) => state.turbo.stop(&id),
+                None => state.turbo.stop_all(),
+            }
+        }
+        ExecutionMessage::SetDebounce(condition, keys, threshold) => {
+            state.debounce.set_threshold(condition, keys, threshold);
+        }
+        ExecutionMessage::GetDebounceCount(tx) => {
+            tx.send(state.debounce.suppressed_count()).await.unwrap();
+        }
+        ExecutionMessage::Scroll(vertical, horizontal, tx) => {
+            tx.send(state.wheels.scroll(vertical, horizontal)).await.unwrap();
+        }
+        ExecutionMessage::MouseKeysPress(action, invocation) => {
+            state.mouse_keys.press(action, invocation, state.layers.active_names(), message_tx);
+        }
+        ExecutionMessage::MouseKeysTick => {
+            for ev in state.mouse_keys.tick(time::Instant::now(), &state.layers.active_names()) {
+                ev_writer.send(ev.into()).await.unwrap();
+            }
+        }
+        ExecutionMessage::MouseKeysDrag(button) => {
+            for ev in state.mouse_keys.toggle_drag(button) {
+                ev_writer.send(ev.into()).await.unwrap();
+            }
+        }
+        ExecutionMessage::SetMouseKeysSpeed(initial_speed, max_speed) => {
+            state.mouse_keys.set_speed(initial_speed, max_speed);
+        }
+        ExecutionMessage::SetMouseKeysAcceleration(acceleration_time, acceleration_exponent) => {
+            state.mouse_keys.set_acceleration(acceleration_time, acceleration_exponent);
+        }
+        ExecutionMessage::SetMouseKeysScrollRate(scroll_rate) => {
+            state.mouse_keys.set_scroll_rate(scroll_rate);
+        }
+        ExecutionMessage::SetScrollEmulation(trigger) => {
+            state.scroll_emulation.set_trigger(trigger);
+        }
+        ExecutionMessage::SetScrollEmulationSensitivity(sensitivity) => {
+            state.scroll_emulation.set_sensitivity(sensitivity);
+        }
+        ExecutionMessage::SetScrollEmulationAxisLock(axis_lock) => {
+            state.scroll_emulation.set_axis_lock(axis_lock);
+        }
+        ExecutionMessage::SetScrollEmulationNatural(natural) => {
+            state.scroll_emulation.set_natural(natural);
+        }
+        ExecutionMessage::SetPointerTransform(condition, setting) => {
+            state.pointer_transform.set(condition, setting);
+        }
+        ExecutionMessage::AddGesture(gesture, block, var_map) => {
+            state.gestures.insert(gesture, Arc::new((block, var_map, MappingOptions::default())));
+        }
+        ExecutionMessage::SetGestureTrigger(trigger) => {
+            state.gestures.set_trigger(trigger);
+        }
+        ExecutionMessage::SetGestureThreshold(threshold) => {
+            state.gestures.set_threshold(threshold);
+        }
+        ExecutionMessage::AddAbsThreshold(axis, percent, key) => {
+            state.abs_thresholds.insert(axis, percent, key);
+        }
+        ExecutionMessage::SetGamepadDeadzone(stick, deadzone) => {
+            state.gamepad.set_deadzone(stick, deadzone);
+        }
+        ExecutionMessage::SetGamepadCurve(stick, curve) => {
+            state.gamepad.set_curve(stick, curve);
+        }
+        ExecutionMessage::SetGamepadSwapSticks(swap_sticks) => {
+            state.gamepad.set_swap_sticks(swap_sticks);
+        }
+        ExecutionMessage::AddGamepadKeyAxis(key, axis, position) => {
+            state.gamepad.insert_key_axis(key, axis, position);
+        }
+        ExecutionMessage::SetJoystickMouse(stick, mode, speed) => {
+            state.joystick_mouse.set_mode(stick, mode, speed);
+        }
+        ExecutionMessage::SetJoystickMouseDeadzone(stick, deadzone) => {
+            state.joystick_mouse.set_deadzone(stick, deadzone);
+        }
+        ExecutionMessage::SetJoystickMouseAcceleration(stick, acceleration) => {
+            state.joystick_mouse.set_acceleration(stick, acceleration);
+        }
+        ExecutionMessage::LockStateChanged(led, locked) => {
+            for ev in state.leds.set_lock_state(led, locked) {
+                ev_writer.send(ev.into()).await.unwrap();
+            }
+        }
+        ExecutionMessage::GetLockState(led, tx) => {
+            tx.send(state.leds.is_locked(&led)).await.unwrap();
+        }
+        ExecutionMessage::SetLed(led, value) => {
+            for ev in state.leds.set_override(led, value) {
+                ev_writer.send(ev.into()).await.unwrap();
+            }
+        }
+        ExecutionMessage::AddLayerLed(layer, led) => {
+            for ev in state.leds.insert_layer_led(layer, led) {
+                ev_writer.send(ev.into()).await.unwrap();
+            }
+        }
+        ExecutionMessage::JoystickMouseTick => {
+            for ev in state.joystick_mouse.tick(time::Instant::now()) {
+                ev_writer.send(ev.into()).await.unwrap();
             }
         }
         ExecutionMessage::GetFocusedWindowInfo(tx) => {
@@ -118,16 +727,29 @@ pub async fn handle_execution_message(
         ExecutionMessage::Write(message) => {
             out.write(message.as_ref()).unwrap();
         }
-        ExecutionMessage::Exit(exit_code) => { std::process::exit(exit_code) }
+        ExecutionMessage::Exit(exit_code) => {
+            state.turbo.stop_all();
+            std::process::exit(exit_code)
+        }
         ExecutionMessage::FatalError(err, exit_code) => {
+            state.turbo.stop_all();
             eprintln!("error: {}", err);
             std::process::exit(exit_code)
         }
     }
+
+    update_layer_leds(state, ev_writer).await;
+}
+
+/// Lights the LEDs of layers that were activated and turns off the ones of deactivated layers.
+async fn update_layer_leds(state: &mut State, ev_writer: &mut mpsc::Sender<OutputEvent>) {
+    for ev in state.leds.set_active_layers(state.layers.active_names()) {
+        ev_writer.send(ev.into()).await.unwrap();
+    }
 }
 
 
-pub fn handle_active_window_change(ev_writer_tx: &mut mpsc::Sender<InputEvent>, message_tx: &mut ExecutionMessageSender,
+pub fn handle_active_window_change(ev_writer_tx: &mut mpsc::Sender<OutputEvent>, message_tx: &mut ExecutionMessageSender,
                                    window_cycle_token: usize, window_change_handlers: &mut Vec<(Block, GuardedVarMap)>) {
     for (handler, var_map) in window_change_handlers {
         let mut message_tx = message_tx.clone();
@@ -143,6 +765,12 @@ pub fn handle_active_window_change(ev_writer_tx: &mut mpsc::Sender<InputEvent>,
                            message_tx: Some(&mut message_tx),
                            window_cycle_token,
                            modifier_state: &KeyModifierState::new(),
+                           layer: None,
+                           condition: KeyActionCondition::new(),
+                           trigger_key: None,
+                           trigger_value: None,
+                           device: None,
+                           invocation: None,
                        },
             ).await;
         });
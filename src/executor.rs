@@ -0,0 +1,209 @@
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+
+use futures::FutureExt;
+use tokio::sync::watch;
+
+use crate::*;
+
+/// Decides how a mapping invocation is ordered relative to other invocations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExecutionMode {
+    /// runs after the previous invocations triggered by the same key finished
+    PerKey,
+    /// runs after the previous invocations of all mappings using this mode finished
+    Global,
+    /// runs immediately, possibly interleaving with other invocations
+    Concurrent,
+}
+
+impl ExecutionMode {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "key" => Ok(ExecutionMode::PerKey),
+            "global" => Ok(ExecutionMode::Global),
+            "concurrent" => Ok(ExecutionMode::Concurrent),
+            _ => Err(anyhow!("unknown execution mode '{}'", name)),
+        }
+    }
+}
+
+impl Default for ExecutionMode {
+    fn default() -> Self { ExecutionMode::PerKey }
+}
+
+/// Options that can be passed when adding a mapping with 'map_key'.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct MappingOptions {
+    pub execution_mode: ExecutionMode,
+    // cancels a mapping triggered by a key press once the key is released
+    pub cancel_on_release: bool,
+}
+
+impl MappingOptions {
+    /// Parses a whitespace separated list of options, i.e. "concurrent cancel_on_release".
+    pub fn from_names(names: &str) -> Result<Self> {
+        let mut options = MappingOptions::default();
+        for name in names.split_whitespace() {
+            match name {
+                "cancel_on_release" => options.cancel_on_release = true,
+                _ => options.execution_mode = ExecutionMode::from_name(name)
+                    .map_err(|_| anyhow!("unknown mapping option '{}'", name))?,
+            }
+        }
+        Ok(options)
+    }
+}
+
+type Job = Pin<Box<dyn Future<Output=()> + Send>>;
+
+/// Runs mapping invocations, each queue executes its invocations one after another in the order they
+/// were triggered.
+pub struct MappingExecutor {
+    key_queues: HashMap<Key, mpsc::UnboundedSender<Job>>,
+    global_queue: Option<mpsc::UnboundedSender<Job>>,
+}
+
+impl MappingExecutor {
+    pub fn new() -> Self {
+        MappingExecutor { key_queues: Default::default(), global_queue: None }
+    }
+
+    pub fn execute(&mut self, key: Key, mode: ExecutionMode, job: impl Future<Output=()> + Send + 'static) {
+        let queue = match mode {
+            ExecutionMode::Concurrent => {
+                task::spawn(job);
+                return;
+            }
+            ExecutionMode::PerKey => self.key_queues.entry(key).or_insert_with(spawn_queue),
+            ExecutionMode::Global => self.global_queue.get_or_insert_with(spawn_queue),
+        };
+
+        let _ = queue.send(Box::pin(job));
+    }
+}
+
+fn spawn_queue() -> mpsc::UnboundedSender<Job> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Job>();
+    task::spawn(async move {
+        while let Some(job) = rx.recv().await {
+            // a panicking job must not take the jobs queued behind it down with it
+            if AssertUnwindSafe(job).catch_unwind().await.is_err() {
+                eprintln!("error: a mapping panicked, continuing with the next one");
+            }
+        }
+    });
+    tx
+}
+
+/// Ties a mapping invocation to the key press that triggered it.
+#[derive(Clone, Debug)]
+pub struct KeyPressInvocation {
+    release_rx: watch::Receiver<bool>,
+    cancel_on_release: bool,
+    on_release: Arc<Mutex<Vec<(Block, GuardedVarMap)>>>,
+}
+
+impl KeyPressInvocation {
+    /// Creates an invocation along with the sender that releases it.
+    pub fn new(cancel_on_release: bool) -> (watch::Sender<bool>, Self) {
+        let (release_tx, release_rx) = watch::channel(false);
+        (release_tx, KeyPressInvocation { release_rx, cancel_on_release, on_release: Default::default() })
+    }
+
+    pub fn is_released(&self) -> bool { *self.release_rx.borrow() }
+
+    pub fn cancels_on_release(&self) -> bool { self.cancel_on_release }
+
+    pub fn is_cancelled(&self) -> bool { self.cancel_on_release && self.is_released() }
+
+    /// Resolves once the key is released, a dropped sender counts as released.
+    pub async fn released(&self) {
+        let mut release_rx = self.release_rx.clone();
+        while !*release_rx.borrow() {
+            if release_rx.changed().await.is_err() { return; }
+        }
+    }
+
+    /// Registers a callback that runs once the key is released.
+    pub fn on_release(&self, block: Block, var_map: GuardedVarMap) {
+        self.on_release.lock().unwrap().push((block, var_map));
+    }
+
+    pub fn take_on_release(&self) -> Vec<(Block, GuardedVarMap)> {
+        std::mem::take(&mut *self.on_release.lock().unwrap())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    /// A job that logs its name once the gate is opened.
+    fn gated_job(name: &'static str, log: &mpsc::UnboundedSender<&'static str>) -> (oneshot::Sender<()>, impl Future<Output=()>) {
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let log = log.clone();
+        (gate_tx, async move {
+            let _ = gate_rx.await;
+            log.send(name).unwrap();
+        })
+    }
+
+    fn job(name: &'static str, log: &mpsc::UnboundedSender<&'static str>) -> impl Future<Output=()> {
+        let log = log.clone();
+        async move { log.send(name).unwrap(); }
+    }
+
+    #[tokio::test]
+    async fn test_per_key_and_concurrent() {
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let mut executor = MappingExecutor::new();
+
+        let (gate, press) = gated_job("a down", &log_tx);
+        executor.execute(*KEY_A, ExecutionMode::PerKey, press);
+        executor.execute(*KEY_A, ExecutionMode::PerKey, job("a up", &log_tx));
+        executor.execute(*KEY_X, ExecutionMode::Concurrent, job("x", &log_tx));
+
+        // the release of 'a' waits for the press, concurrent jobs don't
+        assert_eq!(log_rx.recv().await, Some("x"));
+        gate.send(()).unwrap();
+        assert_eq!(log_rx.recv().await, Some("a down"));
+        assert_eq!(log_rx.recv().await, Some("a up"));
+    }
+
+    #[tokio::test]
+    async fn test_global() {
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let mut executor = MappingExecutor::new();
+
+        let (gate, g) = gated_job("g", &log_tx);
+        executor.execute(*KEY_G, ExecutionMode::Global, g);
+        executor.execute(*KEY_H, ExecutionMode::Global, job("h global", &log_tx));
+        executor.execute(*KEY_H, ExecutionMode::PerKey, job("h", &log_tx));
+
+        assert_eq!(log_rx.recv().await, Some("h"));
+        gate.send(()).unwrap();
+        assert_eq!(log_rx.recv().await, Some("g"));
+        assert_eq!(log_rx.recv().await, Some("h global"));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job() {
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let mut executor = MappingExecutor::new();
+
+        let (gate, press) = gated_job("a down", &log_tx);
+        executor.execute(*KEY_A, ExecutionMode::PerKey, press);
+        executor.execute(*KEY_A, ExecutionMode::PerKey, async { panic!("mapping failed") });
+        executor.execute(*KEY_A, ExecutionMode::PerKey, job("a up", &log_tx));
+
+        // the job queued behind the panicking one still runs
+        gate.send(()).unwrap();
+        assert_eq!(log_rx.recv().await, Some("a down"));
+        assert_eq!(log_rx.recv().await, Some("a up"));
+    }
+}
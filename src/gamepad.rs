@@ -0,0 +1,303 @@
+use std::collections::HashSet;
+
+use evdev_rs::enums::{EV_KEY, EventCode};
+
+use crate::*;
+
+pub const GAMEPAD_STICK_RANGE: (i32, i32) = (-32768, 32767);
+pub const GAMEPAD_TRIGGER_RANGE: (i32, i32) = (0, 255);
+pub const GAMEPAD_HAT_RANGE: (i32, i32) = (-1, 1);
+
+lazy_static! {
+    /// The axes of the virtual gamepad along with their ranges.
+    pub static ref GAMEPAD_AXES: Vec<(Key, (i32, i32))> = vec![
+        (*ABS_X, GAMEPAD_STICK_RANGE),
+        (*ABS_Y, GAMEPAD_STICK_RANGE),
+        (*ABS_RX, GAMEPAD_STICK_RANGE),
+        (*ABS_RY, GAMEPAD_STICK_RANGE),
+        (*ABS_Z, GAMEPAD_TRIGGER_RANGE),
+        (*ABS_RZ, GAMEPAD_TRIGGER_RANGE),
+        (*ABS_HAT0X, GAMEPAD_HAT_RANGE),
+        (*ABS_HAT0Y, GAMEPAD_HAT_RANGE),
+    ];
+
+    /// The buttons of the virtual gamepad.
+    pub static ref GAMEPAD_BUTTONS: Vec<Key> = {
+        use EV_KEY::*;
+        [
+            BTN_SOUTH, BTN_EAST, BTN_NORTH, BTN_WEST, BTN_TL, BTN_TR, BTN_TL2, BTN_TR2,
+            BTN_SELECT, BTN_START, BTN_MODE, BTN_THUMBL, BTN_THUMBR,
+            BTN_DPAD_UP, BTN_DPAD_DOWN, BTN_DPAD_LEFT, BTN_DPAD_RIGHT,
+        ].iter().map(|code| Key { event_code: EventCode::EV_KEY(*code) }).collect()
+    };
+}
+
+/// Whether an event presses a button of the virtual gamepad, these are written to the gamepad regardless of
+/// where they came from.
+pub fn is_gamepad_button(ev: &InputEvent) -> bool {
+    GAMEPAD_BUTTONS.iter().any(|key| key.event_code == ev.event_code)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    pub(crate) fn axes(&self) -> (Key, Key) {
+        match self {
+            Stick::Left => (*ABS_X, *ABS_Y),
+            Stick::Right => (*ABS_RX, *ABS_RY),
+        }
+    }
+
+    pub(crate) fn of_axis(axis: &Key) -> Option<Stick> {
+        [Stick::Left, Stick::Right].iter().copied().find(|stick| {
+            let (x, y) = stick.axes();
+            *axis == x || *axis == y
+        })
+    }
+
+    fn other(&self) -> Stick {
+        match self {
+            Stick::Left => Stick::Right,
+            Stick::Right => Stick::Left,
+        }
+    }
+}
+
+pub fn parse_stick(name: &str) -> Result<Stick> {
+    match &*name.to_lowercase() {
+        "left" => Ok(Stick::Left),
+        "right" => Ok(Stick::Right),
+        _ => Err(anyhow!("unknown stick '{}', expected 'left' or 'right'", name)),
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StickSettings {
+    // the distance from the center (0 to 1) that is ignored
+    pub deadzone: f64,
+    // the exponent of the response curve, values above 1 make small movements more precise
+    pub curve: f64,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self { StickSettings { deadzone: 0.0, curve: 1.0 } }
+}
+
+/// Maps game controllers onto the virtual gamepad.
+///
+/// Axes are normalized from the range of the physical controller, adjusted by the stick settings and scaled
+/// to the range of the virtual gamepad. Keyboard keys can push gamepad axes while they are held.
+pub struct Gamepad {
+    sticks: [StickSettings; 2],
+    swap_sticks: bool,
+    // keys that push an axis while held, as (key, axis, position from -1 to 1)
+    key_axes: Vec<(Key, Key, f64)>,
+    held_keys: HashSet<Key>,
+    // the normalized position of each axis
+    positions: HashMap<EventCode, f64>,
+    // the last value written for each axis
+    output: HashMap<EventCode, i32>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Gamepad {
+            sticks: Default::default(),
+            swap_sticks: false,
+            key_axes: vec![],
+            held_keys: HashSet::new(),
+            positions: HashMap::new(),
+            output: HashMap::new(),
+        }
+    }
+
+    pub fn set_deadzone(&mut self, stick: Stick, deadzone: f64) { self.sticks[stick as usize].deadzone = deadzone; }
+
+    pub fn set_curve(&mut self, stick: Stick, curve: f64) { self.sticks[stick as usize].curve = curve; }
+
+    pub fn set_swap_sticks(&mut self, swap_sticks: bool) { self.swap_sticks = swap_sticks; }
+
+    pub fn insert_key_axis(&mut self, key: Key, axis: Key, position: f64) {
+        self.key_axes.push((key, axis, position));
+    }
+
+    /// Gets the events for the virtual gamepad an input event turned into, events that don't concern the gamepad
+    /// are passed on.
+    pub fn handle_event(&mut self, ev: &InputEvent, device: &DeviceInfo) -> Option<Vec<InputEvent>> {
+        let key = Key { event_code: ev.event_code };
+
+        if self.key_axes.iter().any(|(axis_key, _, _)| *axis_key == key) {
+            if ev.value == TYPE_DOWN {
+                self.held_keys.insert(key);
+            } else if ev.value == TYPE_UP {
+                self.held_keys.remove(&key);
+            } else {
+                return Some(vec![]);
+            }
+
+            let axes = self.key_axes.iter()
+                .filter(|(axis_key, _, _)| *axis_key == key)
+                .map(|(_, axis, _)| *axis)
+                .collect::<Vec<_>>();
+            let mut events = vec![];
+            for axis in axes {
+                let position = self.key_axes.iter()
+                    .filter(|(axis_key, target, _)| *target == axis && self.held_keys.contains(axis_key))
+                    .map(|(_, _, position)| position)
+                    .sum::<f64>();
+                self.positions.insert(axis.event_code, position.max(-1.0).min(1.0));
+                events.extend(self.axis_events(&axis));
+            }
+            if !events.is_empty() { events.push(SYN_REPORT.clone()); }
+            return Some(events);
+        }
+
+        if !device.gamepad { return None; }
+
+        let (min, max) = match (GAMEPAD_AXES.iter().find(|(axis, _)| *axis == key), device.abs_range(&ev.event_code)) {
+            (Some(_), Some(range)) => range,
+            _ => return None,
+        };
+
+        let position = if gamepad_axis_range(&key) == GAMEPAD_TRIGGER_RANGE {
+            (ev.value - min) as f64 / (max - min) as f64
+        } else {
+            let center = (min + max) as f64 / 2.0;
+            (ev.value as f64 - center) / ((max - min) as f64 / 2.0)
+        };
+
+        let axis = match Stick::of_axis(&key) {
+            Some(stick) if self.swap_sticks => {
+                let (x, y) = stick.axes();
+                let (other_x, other_y) = stick.other().axes();
+                if key == x { other_x } else if key == y { other_y } else { key }
+            }
+            _ => key,
+        };
+
+        self.positions.insert(axis.event_code, position.max(-1.0).min(1.0));
+        Some(self.axis_events(&axis))
+    }
+
+    /// Gets the events that bring the virtual gamepad axes affected by an axis up to date.
+    fn axis_events(&mut self, axis: &Key) -> Vec<InputEvent> {
+        let values = match Stick::of_axis(axis) {
+            Some(stick) => {
+                let (x, y) = stick.axes();
+                let settings = self.sticks[stick as usize];
+                let position = |axis: &Key| *self.positions.get(&axis.event_code).unwrap_or(&0.0);
+                let (pos_x, pos_y) = apply_stick_settings(position(&x), position(&y), &settings);
+                vec![(x, pos_x), (y, pos_y)]
+            }
+            None => vec![(*axis, *self.positions.get(&axis.event_code).unwrap_or(&0.0))],
+        };
+
+        let mut events = vec![];
+        for (axis, position) in values {
+            let value = scale_to_range(position, gamepad_axis_range(&axis));
+            // the axes of the virtual gamepad start out centered
+            if *self.output.get(&axis.event_code).unwrap_or(&0) == value { continue; }
+            self.output.insert(axis.event_code, value);
+            events.push(KeyAction::new(axis, value).to_input_ev());
+        }
+        events
+    }
+}
+
+fn gamepad_axis_range(axis: &Key) -> (i32, i32) {
+    GAMEPAD_AXES.iter()
+        .find(|(gamepad_axis, _)| gamepad_axis == axis)
+        .map(|(_, range)| *range)
+        .unwrap_or(GAMEPAD_STICK_RANGE)
+}
+
+/// Applies the radial deadzone and response curve to a stick position.
+pub(crate) fn apply_stick_settings(x: f64, y: f64, settings: &StickSettings) -> (f64, f64) {
+    let magnitude = x.hypot(y);
+    if magnitude <= settings.deadzone || magnitude == 0.0 { return (0.0, 0.0); }
+
+    let adjusted = ((magnitude.min(1.0) - settings.deadzone) / (1.0 - settings.deadzone)).powf(settings.curve);
+    (x / magnitude * adjusted, y / magnitude * adjusted)
+}
+
+/// Scales a normalized position (-1 to 1, or 0 to 1 for triggers) to a range.
+fn scale_to_range(position: f64, (min, max): (i32, i32)) -> i32 {
+    ((position * max as f64).round() as i32).max(min).min(max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn controller() -> DeviceInfo {
+        gamepad(vec![
+            (ABS_X.event_code, 0, 255),
+            (ABS_Y.event_code, 0, 255),
+            (ABS_RX.event_code, 0, 255),
+            (ABS_Z.event_code, 0, 1023),
+        ])
+    }
+
+    #[test]
+    fn test_axis_scaling() {
+        let mut gamepad = Gamepad::new();
+        let device = controller();
+
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 255), &device), Some(vec![ev(*ABS_X, 32767)]));
+        assert_eq!(gamepad.handle_event(&ev(*ABS_Z, 1023), &device), Some(vec![ev(*ABS_Z, 255)]));
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 0), &device), Some(vec![ev(*ABS_X, -32767)]));
+
+        // events of other devices and axes without a known range pass through
+        let mut tablet = device.clone();
+        tablet.gamepad = false;
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 5), &tablet), None);
+        assert_eq!(gamepad.handle_event(&ev(*ABS_HAT0X, 1), &device), None);
+    }
+
+    #[test]
+    fn test_deadzone() {
+        let mut gamepad = Gamepad::new();
+        gamepad.set_deadzone(Stick::Left, 0.5);
+        let device = controller();
+
+        // the center doesn't move, positions inside the deadzone stay centered
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 170), &device), Some(vec![]));
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 200), &device), Some(vec![ev(*ABS_X, 4497)]));
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 255), &device), Some(vec![ev(*ABS_X, 32767)]));
+    }
+
+    #[test]
+    fn test_swap_sticks() {
+        let mut gamepad = Gamepad::new();
+        gamepad.set_swap_sticks(true);
+        let device = controller();
+
+        assert_eq!(gamepad.handle_event(&ev(*ABS_X, 255), &device), Some(vec![ev(*ABS_RX, 32767)]));
+        assert_eq!(gamepad.handle_event(&ev(*ABS_RX, 0), &device), Some(vec![ev(*ABS_X, -32767)]));
+    }
+
+    #[test]
+    fn test_key_axes() {
+        let mut gamepad = Gamepad::new();
+        gamepad.insert_key_axis(*KEY_A, *ABS_X, -1.0);
+        gamepad.insert_key_axis(*KEY_D, *ABS_X, 1.0);
+        let device = controller();
+
+        assert_eq!(gamepad.handle_event(&ev(*KEY_A, TYPE_DOWN), &device), Some(vec![
+            ev(*ABS_X, -32767),
+            SYN_REPORT.clone(),
+        ]));
+        assert_eq!(gamepad.handle_event(&ev(*KEY_A, TYPE_REPEAT), &device), Some(vec![]));
+        // opposite keys cancel each other out
+        assert_eq!(gamepad.handle_event(&ev(*KEY_D, TYPE_DOWN), &device), Some(vec![ev(*ABS_X, 0), SYN_REPORT.clone()]));
+        assert_eq!(gamepad.handle_event(&ev(*KEY_A, TYPE_UP), &device), Some(vec![ev(*ABS_X, 32767), SYN_REPORT.clone()]));
+        assert_eq!(gamepad.handle_event(&ev(*KEY_S, TYPE_DOWN), &device), None);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::*;
+
+// the pointer is released once this many events were held back, even if a gesture could still be drawn
+const MAX_BUFFERED_EVENTS: usize = 500;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GestureDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Parses a gesture written as space separated directions, i.e. 'down right'.
+pub fn parse_gesture(raw: &str) -> Result<Vec<GestureDirection>> {
+    let mut gesture = vec![];
+    for word in raw.split_whitespace() {
+        let direction = match word {
+            "up" => GestureDirection::Up,
+            "down" => GestureDirection::Down,
+            "left" => GestureDirection::Left,
+            "right" => GestureDirection::Right,
+            _ => return Err(anyhow!("invalid gesture direction '{}'", word)),
+        };
+        // consecutive strokes in the same direction can't be told apart
+        if gesture.last() == Some(&direction) { return Err(anyhow!("gesture '{}' repeats a direction", raw)); }
+        gesture.push(direction);
+    }
+    if gesture.is_empty() { return Err(anyhow!("no gesture directions given")); }
+    Ok(gesture)
+}
+
+/// The events an input event turned into, along with the callback of a recognized gesture.
+pub struct GestureOutput {
+    pub events: Vec<InputEvent>,
+    pub callback: Option<CompiledKeyMapping>,
+}
+
+impl GestureOutput {
+    fn events(events: Vec<InputEvent>) -> Self { GestureOutput { events, callback: None } }
+}
+
+/// Recognizes strokes drawn while the trigger button is held.
+///
+/// The pointer doesn't move while the trigger is held, each stroke in a new direction is added to the gesture
+/// once the pointer traveled the threshold distance. As soon as the strokes can't become a defined gesture
+/// anymore, or if the gesture isn't defined on release, the trigger press and the buffered movement are
+/// replayed.
+pub struct Gestures {
+    trigger: Key,
+    threshold: i32,
+    definitions: HashMap<Vec<GestureDirection>, CompiledKeyMapping>,
+    held: bool,
+    buffer: Vec<InputEvent>,
+    // the movement since the last recognized stroke
+    stroke: (i32, i32),
+    directions: Vec<GestureDirection>,
+}
+
+impl Gestures {
+    pub fn new() -> Self {
+        Gestures {
+            trigger: *BTN_RIGHT,
+            threshold: 30,
+            definitions: Default::default(),
+            held: false,
+            buffer: vec![],
+            stroke: (0, 0),
+            directions: vec![],
+        }
+    }
+
+    pub fn insert(&mut self, gesture: Vec<GestureDirection>, mapping: CompiledKeyMapping) {
+        self.definitions.insert(gesture, mapping);
+    }
+
+    pub fn set_trigger(&mut self, trigger: Key) {
+        self.trigger = trigger;
+        self.held = false;
+    }
+
+    pub fn set_threshold(&mut self, threshold: i32) { self.threshold = threshold; }
+
+    pub fn handle_event(&mut self, ev: &InputEvent) -> GestureOutput {
+        if self.definitions.is_empty() { return GestureOutput::events(vec![ev.clone()]); }
+
+        if ev.event_code == self.trigger.event_code {
+            if ev.value == TYPE_DOWN {
+                self.held = true;
+                self.buffer.clear();
+                self.stroke = (0, 0);
+                self.directions.clear();
+                return GestureOutput::events(vec![]);
+            }
+            if !self.held { return GestureOutput::events(vec![ev.clone()]); }
+            if ev.value != TYPE_UP { return GestureOutput::events(vec![]); }
+
+            if let Some(callback) = self.definitions.get(&self.directions) {
+                self.held = false;
+                self.buffer.clear();
+                return GestureOutput { events: vec![], callback: Some(callback.clone()) };
+            }
+
+            let mut events = self.replay();
+            events.push(ev.clone());
+            return GestureOutput::events(events);
+        }
+
+        if !self.held { return GestureOutput::events(vec![ev.clone()]); }
+
+        match ev.event_code {
+            EventCode::EV_REL(_) if ev.event_code == REL_X.event_code => self.stroke.0 += ev.value,
+            EventCode::EV_REL(_) if ev.event_code == REL_Y.event_code => self.stroke.1 += ev.value,
+            EventCode::EV_SYN(_) => {}
+            _ => return GestureOutput::events(vec![ev.clone()]),
+        }
+        self.buffer.push(ev.clone());
+        if self.buffer.len() > MAX_BUFFERED_EVENTS { return GestureOutput::events(self.replay()); }
+
+        let (x, y) = self.stroke;
+        if x.abs().max(y.abs()) >= self.threshold {
+            let direction = match (x.abs() >= y.abs(), x > 0, y > 0) {
+                (true, true, _) => GestureDirection::Right,
+                (true, false, _) => GestureDirection::Left,
+                (false, _, true) => GestureDirection::Down,
+                (false, _, false) => GestureDirection::Up,
+            };
+            if self.directions.last() != Some(&direction) { self.directions.push(direction); }
+            self.stroke = (0, 0);
+
+            // the trigger keeps its original function (i.e. dragging) if no gesture starts like this
+            if !self.definitions.keys().any(|gesture| gesture.starts_with(&self.directions)) {
+                return GestureOutput::events(self.replay());
+            }
+        }
+        GestureOutput::events(vec![])
+    }
+
+    /// Stops recognizing the gesture, gets the held back trigger press and movement. The following events of
+    /// the trigger are passed through.
+    fn replay(&mut self) -> Vec<InputEvent> {
+        self.held = false;
+        let mut events = vec![KeyAction::new(self.trigger, TYPE_DOWN).to_input_ev(), SYN_REPORT.clone()];
+        events.extend(std::mem::take(&mut self.buffer));
+        events
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn gestures() -> Gestures {
+        let mut gestures = Gestures::new();
+        gestures.insert(parse_gesture("down right").unwrap(), mapping());
+        gestures
+    }
+
+    #[test]
+    fn test_parse_gesture() {
+        use GestureDirection::*;
+        assert_eq!(parse_gesture("up left down").unwrap(), vec![Up, Left, Down]);
+        assert!(parse_gesture("left left").is_err());
+        assert!(parse_gesture("").is_err());
+    }
+
+    #[test]
+    fn test_recognized_gesture() {
+        let mut gestures = gestures();
+        assert!(gestures.handle_event(&ev(*BTN_RIGHT, TYPE_DOWN)).events.is_empty());
+        for _ in 0..4 {
+            assert!(gestures.handle_event(&ev(*REL_Y, 10)).events.is_empty());
+            gestures.handle_event(&ev(*REL_X, 2));
+        }
+        for _ in 0..4 { gestures.handle_event(&ev(*REL_X, 10)); }
+        assert_eq!(gestures.directions, vec![GestureDirection::Down, GestureDirection::Right]);
+
+        let output = gestures.handle_event(&ev(*BTN_RIGHT, TYPE_UP));
+        assert!(output.callback.is_some());
+        assert!(output.events.is_empty());
+    }
+
+    #[test]
+    fn test_replayed_click() {
+        let mut gestures = gestures();
+        gestures.handle_event(&ev(*BTN_RIGHT, TYPE_DOWN));
+        gestures.handle_event(&ev(*REL_X, 5));
+        let output = gestures.handle_event(&ev(*BTN_RIGHT, TYPE_UP));
+        assert!(output.callback.is_none());
+        assert_eq!(output.events, vec![
+            ev(*BTN_RIGHT, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*REL_X, 5),
+            ev(*BTN_RIGHT, TYPE_UP),
+        ]);
+
+        // other buttons aren't affected
+        assert_eq!(gestures.handle_event(&ev(*BTN_LEFT, TYPE_DOWN)).events, vec![ev(*BTN_LEFT, TYPE_DOWN)]);
+    }
+
+    #[test]
+    fn test_aborted_gesture() {
+        let mut gestures = gestures();
+        gestures.handle_event(&ev(*BTN_RIGHT, TYPE_DOWN));
+        gestures.handle_event(&ev(*REL_X, 20));
+
+        // no gesture starts to the right, the drag is released right away
+        assert_eq!(gestures.handle_event(&ev(*REL_X, 20)).events, vec![
+            ev(*BTN_RIGHT, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*REL_X, 20),
+            ev(*REL_X, 20),
+        ]);
+        assert_eq!(gestures.handle_event(&ev(*REL_X, 20)).events, vec![ev(*REL_X, 20)]);
+        let output = gestures.handle_event(&ev(*BTN_RIGHT, TYPE_UP));
+        assert!(output.callback.is_none());
+        assert_eq!(output.events, vec![ev(*BTN_RIGHT, TYPE_UP)]);
+    }
+
+    #[test]
+    fn test_buffer_limit() {
+        let mut gestures = gestures();
+        gestures.handle_event(&ev(*BTN_RIGHT, TYPE_DOWN));
+        for _ in 0..MAX_BUFFERED_EVENTS {
+            assert!(gestures.handle_event(&SYN_REPORT.clone()).events.is_empty());
+        }
+        assert_eq!(gestures.handle_event(&SYN_REPORT.clone()).events.len(), MAX_BUFFERED_EVENTS + 3);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::*;
+use crate::messaging::{ExecutionMessage, ExecutionMessageSender};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JoystickMouseMode {
+    Pointer,
+    Scroll,
+}
+
+impl JoystickMouseMode {
+    /// The speed at full deflection, in pixels per second when moving the pointer and in wheel notches per
+    /// second when scrolling.
+    pub fn default_speed(&self) -> f64 {
+        match self {
+            JoystickMouseMode::Pointer => 1200.0,
+            JoystickMouseMode::Scroll => 15.0,
+        }
+    }
+}
+
+pub fn parse_joystick_mouse_mode(name: &str) -> Result<JoystickMouseMode> {
+    match &*name.to_lowercase() {
+        "pointer" => Ok(JoystickMouseMode::Pointer),
+        "scroll" => Ok(JoystickMouseMode::Scroll),
+        _ => Err(anyhow!("unknown mode '{}', expected 'pointer' or 'scroll'", name)),
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct StickMouseSettings {
+    mode: Option<JoystickMouseMode>,
+    speed: f64,
+    // the deadzone and the curve of the speed, curves above 1 make small movements more precise
+    stick: StickSettings,
+}
+
+impl Default for StickMouseSettings {
+    fn default() -> Self {
+        StickMouseSettings { mode: None, speed: 0.0, stick: StickSettings { deadzone: 0.15, curve: 2.0 } }
+    }
+}
+
+/// Moves the pointer or scrolls with the sticks of game controllers.
+///
+/// Sticks that drive the mouse are consumed, the pointer moves continuously on every tick while a stick is
+/// pushed past its deadzone.
+pub struct JoystickMouse {
+    sticks: [StickMouseSettings; 2],
+    // the normalized position of each stick axis
+    positions: HashMap<EventCode, f64>,
+    ticker: PointerTicker,
+    // partial wheel notches carried over to the next tick
+    wheel: (WheelAccumulator, WheelAccumulator),
+}
+
+impl JoystickMouse {
+    pub fn new() -> Self {
+        JoystickMouse {
+            sticks: Default::default(),
+            positions: HashMap::new(),
+            ticker: PointerTicker::new(),
+            wheel: Default::default(),
+        }
+    }
+
+    /// Lets a stick drive the mouse, the stick is passed through again if no mode is given.
+    pub fn set_mode(&mut self, stick: Stick, mode: Option<JoystickMouseMode>, speed: f64) {
+        let settings = &mut self.sticks[stick as usize];
+        settings.mode = mode;
+        settings.speed = speed;
+    }
+
+    pub fn set_deadzone(&mut self, stick: Stick, deadzone: f64) { self.sticks[stick as usize].stick.deadzone = deadzone; }
+
+    pub fn set_acceleration(&mut self, stick: Stick, acceleration: f64) {
+        self.sticks[stick as usize].stick.curve = acceleration;
+    }
+
+    /// Gets the events an input event turned into.
+    pub fn handle_event(&mut self, ev: &InputEvent, device: &DeviceInfo) -> Vec<InputEvent> {
+        let axis = Key { event_code: ev.event_code };
+        match Stick::of_axis(&axis) {
+            Some(stick) if device.gamepad && self.sticks[stick as usize].mode.is_some() => {}
+            _ => return vec![ev.clone()],
+        }
+        let (min, max) = match device.abs_range(&ev.event_code) {
+            Some(range) => range,
+            None => return vec![ev.clone()],
+        };
+
+        let center = (min + max) as f64 / 2.0;
+        let position = (ev.value as f64 - center) / ((max - min) as f64 / 2.0);
+        self.positions.insert(ev.event_code, position.max(-1.0).min(1.0));
+        vec![]
+    }
+
+    /// Starts ticking once a stick is pushed past its deadzone.
+    pub fn update_ticker(&mut self, message_tx: &ExecutionMessageSender) {
+        if !self.is_moving() { return; }
+        self.ticker.start(message_tx, || ExecutionMessage::JoystickMouseTick);
+    }
+
+    fn is_moving(&self) -> bool {
+        [Stick::Left, Stick::Right].iter().any(|stick| self.deflection(*stick).is_some())
+    }
+
+    /// The position of a stick after applying the deadzone and curve, if it drives the mouse and is pushed.
+    fn deflection(&self, stick: Stick) -> Option<(f64, f64)> {
+        let settings = &self.sticks[stick as usize];
+        settings.mode?;
+
+        let (x, y) = stick.axes();
+        let position = |axis: &Key| *self.positions.get(&axis.event_code).unwrap_or(&0.0);
+        match apply_stick_settings(position(&x), position(&y), &settings.stick) {
+            (x, y) if x == 0.0 && y == 0.0 => None,
+            deflection => Some(deflection),
+        }
+    }
+
+    /// Gets the events for the time passed since the last tick.
+    pub fn tick(&mut self, now: time::Instant) -> Vec<InputEvent> {
+        if !self.is_moving() {
+            self.ticker.stop();
+            self.wheel = Default::default();
+            return vec![];
+        }
+
+        let dt = self.ticker.elapsed(now);
+        let (mut dx, mut dy) = (0.0, 0.0);
+        let mut scroll_events = vec![];
+        for stick in [Stick::Left, Stick::Right].iter() {
+            let (x, y) = match self.deflection(*stick) {
+                Some(deflection) => deflection,
+                None => continue,
+            };
+            let settings = self.sticks[*stick as usize];
+            let distance = settings.speed * dt;
+
+            match settings.mode {
+                Some(JoystickMouseMode::Pointer) => {
+                    dx += x * distance;
+                    dy += y * distance;
+                }
+                Some(JoystickMouseMode::Scroll) => {
+                    // pushing the stick up scrolls up
+                    let units = distance * WHEEL_HI_RES_UNITS;
+                    scroll_events.extend(self.wheel.0.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, -y * units));
+                    scroll_events.extend(self.wheel.1.scroll(*REL_HWHEEL, *REL_HWHEEL_HI_RES, x * units));
+                }
+                None => {}
+            }
+        }
+
+        let mut events = self.ticker.move_events(dx, dy);
+        events.extend(scroll_events);
+
+        if !events.is_empty() { events.push(SYN_REPORT.clone()); }
+        events
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn controller() -> DeviceInfo {
+        gamepad(vec![
+            (ABS_X.event_code, -100, 100),
+            (ABS_Y.event_code, -100, 100),
+            (ABS_RX.event_code, -100, 100),
+            (ABS_RY.event_code, -100, 100),
+        ])
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_joystick_mouse_mode("Pointer").unwrap(), JoystickMouseMode::Pointer);
+        assert!(parse_joystick_mouse_mode("wheel").is_err());
+    }
+
+    #[test]
+    fn test_pointer() {
+        let device = controller();
+        let mut joystick_mouse = JoystickMouse::new();
+        joystick_mouse.set_mode(Stick::Left, Some(JoystickMouseMode::Pointer), 1000.0);
+        joystick_mouse.set_acceleration(Stick::Left, 1.0);
+        joystick_mouse.set_deadzone(Stick::Left, 0.0);
+
+        // only the enabled stick is consumed
+        assert_eq!(joystick_mouse.handle_event(&ev(*ABS_X, 50), &device), vec![]);
+        assert_eq!(joystick_mouse.handle_event(&ev(*ABS_RX, 50), &device), vec![ev(*ABS_RX, 50)]);
+
+        let start = joystick_mouse.ticker.last_tick;
+        assert_eq!(joystick_mouse.tick(start + time::Duration::from_millis(10)), vec![
+            ev(*REL_X, 5),
+            SYN_REPORT.clone(),
+        ]);
+
+        joystick_mouse.handle_event(&ev(*ABS_X, 0), &device);
+        assert_eq!(joystick_mouse.tick(start + time::Duration::from_millis(20)), vec![]);
+    }
+
+    #[test]
+    fn test_deadzone_and_acceleration() {
+        let device = controller();
+        let mut joystick_mouse = JoystickMouse::new();
+        joystick_mouse.set_mode(Stick::Left, Some(JoystickMouseMode::Pointer), 1000.0);
+        joystick_mouse.set_deadzone(Stick::Left, 0.5);
+
+        joystick_mouse.handle_event(&ev(*ABS_Y, -40), &device);
+        assert!(!joystick_mouse.is_moving());
+
+        // 75% deflection is half way between the deadzone and the end, which squared gives a quarter of the speed
+        joystick_mouse.handle_event(&ev(*ABS_Y, -75), &device);
+        let start = joystick_mouse.ticker.last_tick;
+        assert_eq!(joystick_mouse.tick(start + time::Duration::from_millis(40)), vec![
+            ev(*REL_Y, -10),
+            SYN_REPORT.clone(),
+        ]);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let device = controller();
+        let mut joystick_mouse = JoystickMouse::new();
+        joystick_mouse.set_mode(Stick::Right, Some(JoystickMouseMode::Scroll), 10.0);
+        joystick_mouse.set_acceleration(Stick::Right, 1.0);
+        joystick_mouse.set_deadzone(Stick::Right, 0.0);
+
+        joystick_mouse.handle_event(&ev(*ABS_RY, -100), &device);
+        let start = joystick_mouse.ticker.last_tick;
+        assert_eq!(joystick_mouse.tick(start + time::Duration::from_millis(50)), vec![
+            ev(*REL_WHEEL_HI_RES, 60),
+            SYN_REPORT.clone(),
+        ]);
+        assert_eq!(joystick_mouse.tick(start + time::Duration::from_millis(100)), vec![
+            ev(*REL_WHEEL_HI_RES, 60),
+            ev(*REL_WHEEL, 1),
+            SYN_REPORT.clone(),
+        ]);
+    }
+}
@@ -22,14 +22,19 @@ pub static ref KEY_ESC: Key = Key::from_str(&EventType::EV_KEY, "KEY_ESC").unwra
 pub static ref KEY_TAB: Key = Key::from_str(&EventType::EV_KEY, "KEY_TAB").unwrap();
 pub static ref KEY_SPACE: Key = Key::from_str(&EventType::EV_KEY, "KEY_SPACE").unwrap();
 pub static ref KEY_MINUS: Key = Key::from_str(&EventType::EV_KEY, "KEY_MINUS").unwrap();
+pub static ref KEY_BACKSPACE: Key = Key::from_str(&EventType::EV_KEY, "KEY_BACKSPACE").unwrap();
 pub static ref KEY_SLASH: Key = Key::from_str(&EventType::EV_KEY, "KEY_SLASH").unwrap();
 pub static ref KEY_CAPSLOCK: Key = Key::from_str(&EventType::EV_KEY, "KEY_CAPSLOCK").unwrap();
 pub static ref KEY_LEFT: Key = Key::from_str(&EventType::EV_KEY, "KEY_LEFT").unwrap();
 pub static ref KEY_RIGHT: Key = Key::from_str(&EventType::EV_KEY, "KEY_RIGHT").unwrap();
 pub static ref KEY_UP: Key = Key::from_str(&EventType::EV_KEY, "KEY_UP").unwrap();
 pub static ref KEY_DOWN: Key = Key::from_str(&EventType::EV_KEY, "KEY_DOWN").unwrap();
+pub static ref KEY_F1: Key = Key::from_str(&EventType::EV_KEY, "KEY_F1").unwrap();
+pub static ref KEY_F2: Key = Key::from_str(&EventType::EV_KEY, "KEY_F2").unwrap();
+pub static ref KEY_F3: Key = Key::from_str(&EventType::EV_KEY, "KEY_F3").unwrap();
 pub static ref KEY_F4: Key = Key::from_str(&EventType::EV_KEY, "KEY_F4").unwrap();
 pub static ref KEY_F5: Key = Key::from_str(&EventType::EV_KEY, "KEY_F5").unwrap();
+pub static ref KEY_F9: Key = Key::from_str(&EventType::EV_KEY, "KEY_F9").unwrap();
 pub static ref KEY_A: Key = Key::from_str(&EventType::EV_KEY, "KEY_A").unwrap();
 pub static ref KEY_B: Key = Key::from_str(&EventType::EV_KEY, "KEY_B").unwrap();
 pub static ref KEY_C: Key = Key::from_str(&EventType::EV_KEY, "KEY_C").unwrap();
@@ -66,6 +71,35 @@ pub static ref KEY_KPD6: Key = Key::from_str(&EventType::EV_KEY, "KEY_KP6").unwr
 pub static ref KEY_KPD7: Key = Key::from_str(&EventType::EV_KEY, "KEY_KP7").unwrap();
 }
 
+lazy_static! {
+pub static ref KEY_VOLUME_UP: Key = Key::from_str(&EventType::EV_KEY, "KEY_VOLUMEUP").unwrap();
+pub static ref KEY_VOLUME_DOWN: Key = Key::from_str(&EventType::EV_KEY, "KEY_VOLUMEDOWN").unwrap();
+pub static ref REL_X: Key = Key::from_str(&EventType::EV_REL, "REL_X").unwrap();
+pub static ref REL_Y: Key = Key::from_str(&EventType::EV_REL, "REL_Y").unwrap();
+pub static ref REL_WHEEL: Key = Key::from_str(&EventType::EV_REL, "REL_WHEEL").unwrap();
+pub static ref REL_HWHEEL: Key = Key::from_str(&EventType::EV_REL, "REL_HWHEEL").unwrap();
+pub static ref REL_WHEEL_HI_RES: Key = Key::from_str(&EventType::EV_REL, "REL_WHEEL_HI_RES").unwrap();
+pub static ref REL_HWHEEL_HI_RES: Key = Key::from_str(&EventType::EV_REL, "REL_HWHEEL_HI_RES").unwrap();
+pub static ref BTN_LEFT: Key = Key::from_str(&EventType::EV_KEY, "BTN_LEFT").unwrap();
+pub static ref BTN_RIGHT: Key = Key::from_str(&EventType::EV_KEY, "BTN_RIGHT").unwrap();
+pub static ref BTN_MIDDLE: Key = Key::from_str(&EventType::EV_KEY, "BTN_MIDDLE").unwrap();
+pub static ref BTN_SIDE: Key = Key::from_str(&EventType::EV_KEY, "BTN_SIDE").unwrap();
+pub static ref BTN_EXTRA: Key = Key::from_str(&EventType::EV_KEY, "BTN_EXTRA").unwrap();
+pub static ref ABS_X: Key = Key::from_str(&EventType::EV_ABS, "ABS_X").unwrap();
+pub static ref ABS_Y: Key = Key::from_str(&EventType::EV_ABS, "ABS_Y").unwrap();
+pub static ref ABS_Z: Key = Key::from_str(&EventType::EV_ABS, "ABS_Z").unwrap();
+pub static ref ABS_RX: Key = Key::from_str(&EventType::EV_ABS, "ABS_RX").unwrap();
+pub static ref ABS_RY: Key = Key::from_str(&EventType::EV_ABS, "ABS_RY").unwrap();
+pub static ref ABS_RZ: Key = Key::from_str(&EventType::EV_ABS, "ABS_RZ").unwrap();
+pub static ref ABS_HAT0X: Key = Key::from_str(&EventType::EV_ABS, "ABS_HAT0X").unwrap();
+pub static ref ABS_HAT0Y: Key = Key::from_str(&EventType::EV_ABS, "ABS_HAT0Y").unwrap();
+pub static ref BTN_SOUTH: Key = Key::from_str(&EventType::EV_KEY, "BTN_SOUTH").unwrap();
+pub static ref BTN_EAST: Key = Key::from_str(&EventType::EV_KEY, "BTN_EAST").unwrap();
+pub static ref LED_NUML: Key = Key::from_str(&EventType::EV_LED, "LED_NUML").unwrap();
+pub static ref LED_CAPSL: Key = Key::from_str(&EventType::EV_LED, "LED_CAPSL").unwrap();
+pub static ref LED_SCROLLL: Key = Key::from_str(&EventType::EV_LED, "LED_SCROLLL").unwrap();
+}
+
 
 lazy_static! {
     pub(crate) static ref KEY_ALIAS_TABLE: HashMap<&'static str, (Key, KeyModifierFlags)> = {
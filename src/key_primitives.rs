@@ -1,3 +1,5 @@
+use std::cmp::Reverse;
+
 use evdev_rs::enums::{EventCode, EventType};
 
 use crate::*;
@@ -14,6 +16,19 @@ impl Key {
             None => { Err(anyhow!("key not found: '{}'", s)) }
         }
     }
+
+    /// The character of keys named after a single letter or digit, i.e. 'A' for 'KEY_A'.
+    pub fn char(&self) -> Option<char> {
+        let name = self.event_code.to_string();
+        name.strip_prefix("KEY_").filter(|v| v.len() == 1).and_then(|v| v.chars().next())
+    }
+
+    pub fn is_modifier(&self) -> bool {
+        [
+            *KEY_LEFT_CTRL, *KEY_RIGHT_CTRL, *KEY_LEFT_ALT, *KEY_RIGHT_ALT,
+            *KEY_LEFT_SHIFT, *KEY_RIGHT_SHIFT, *KEY_LEFT_META, *KEY_RIGHT_META,
+        ].contains(self)
+    }
 }
 
 
@@ -32,27 +47,118 @@ impl KeyValue {
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ModifierSide { Any, Left, Right }
+
+impl ModifierSide {
+    fn from_state(left: bool, right: bool) -> Self {
+        match (left, right) {
+            (true, false) => ModifierSide::Left,
+            (false, true) => ModifierSide::Right,
+            _ => ModifierSide::Any,
+        }
+    }
+
+    fn key(&self, left: Key, right: Key) -> Key {
+        match self {
+            ModifierSide::Right => right,
+            _ => left,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct KeyModifierFlags {
     pub ctrl: bool,
     pub shift: bool,
     pub alt: bool,
     pub meta: bool,
+    // restricts the modifiers to one side of the keyboard, only relevant if the modifier is set
+    pub ctrl_side: ModifierSide,
+    pub shift_side: ModifierSide,
+    pub alt_side: ModifierSide,
+    pub meta_side: ModifierSide,
+    // a key declared as modifier by the script, i.e. 'capslock' in 'capslock & h'
+    pub custom: Option<Key>,
+    // matches regardless of additional modifiers being pressed
+    pub wildcard: bool,
 }
 
 impl KeyModifierFlags {
-    pub fn new() -> Self { KeyModifierFlags { ctrl: false, shift: false, alt: false, meta: false } }
+    pub fn new() -> Self {
+        KeyModifierFlags {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+            ctrl_side: ModifierSide::Any,
+            shift_side: ModifierSide::Any,
+            alt_side: ModifierSide::Any,
+            meta_side: ModifierSide::Any,
+            custom: None,
+            wildcard: false,
+        }
+    }
     pub fn ctrl(&mut self) { self.ctrl = true; }
     pub fn alt(&mut self) { self.alt = true; }
     pub fn shift(&mut self) { self.shift = true; }
     pub fn meta(&mut self) {
         self.meta = true;
     }
+    pub fn wildcard(&mut self) { self.wildcard = true; }
     pub fn apply_from(&mut self, other: &KeyModifierFlags) {
-        if other.ctrl { self.ctrl(); }
-        if other.alt { self.alt(); }
-        if other.shift { self.shift(); }
-        if other.meta { self.meta(); }
+        if other.ctrl { self.ctrl(); self.ctrl_side = other.ctrl_side; }
+        if other.alt { self.alt(); self.alt_side = other.alt_side; }
+        if other.shift { self.shift(); self.shift_side = other.shift_side; }
+        if other.meta { self.meta(); self.meta_side = other.meta_side; }
+        if other.custom.is_some() { self.custom = other.custom; }
+        if other.wildcard { self.wildcard(); }
+    }
+
+    // the keys used to press the modifiers, the left side is used unless the right side is specified
+    pub fn ctrl_key(&self) -> Key { self.ctrl_side.key(*KEY_LEFT_CTRL, *KEY_RIGHT_CTRL) }
+    pub fn shift_key(&self) -> Key { self.shift_side.key(*KEY_LEFT_SHIFT, *KEY_RIGHT_SHIFT) }
+    pub fn alt_key(&self) -> Key { self.alt_side.key(*KEY_LEFT_ALT, *KEY_RIGHT_ALT) }
+    pub fn meta_key(&self) -> Key { self.meta_side.key(*KEY_LEFT_META, *KEY_RIGHT_META) }
+
+    /// Ranks the modifiers of a trigger against the held modifiers, lower ranks are more specific and triggers
+    /// that don't match have no rank. Triggers that include the held custom modifier come first, exact triggers
+    /// come before wildcard triggers.
+    pub fn match_rank(&self, held: &KeyModifierFlags) -> Option<(bool, bool, Reverse<usize>, Reverse<usize>, [u8; 4])> {
+        if self.custom.is_some() && self.custom != held.custom { return None; }
+
+        let pairs = [
+            (self.ctrl, self.ctrl_side, held.ctrl, held.ctrl_side),
+            (self.shift, self.shift_side, held.shift, held.shift_side),
+            (self.alt, self.alt_side, held.alt, held.alt_side),
+            (self.meta, self.meta_side, held.meta, held.meta_side),
+        ];
+
+        // held modifiers can be matched by side specific triggers or triggers that accept both sides,
+        // wildcard triggers don't need to include them at all
+        let mut order = [0u8; 4];
+        for (idx, (is_set, side, is_held, held_side)) in pairs.iter().enumerate() {
+            order[idx] = match (is_set, is_held) {
+                (false, false) => 3,
+                (false, true) if self.wildcard => 3,
+                (true, true) if *held_side == ModifierSide::Any || side == held_side || *side == ModifierSide::Any => {
+                    match side {
+                        ModifierSide::Left => 0,
+                        ModifierSide::Right => 1,
+                        ModifierSide::Any => 2,
+                    }
+                }
+                _ => return None,
+            };
+        }
+
+        Some((
+            self.custom.is_none(),
+            self.wildcard,
+            Reverse(order.iter().filter(|option| **option < 3).count()),
+            Reverse(order.iter().filter(|option| **option < 2).count()),
+            order,
+        ))
     }
 }
 
@@ -85,6 +191,20 @@ impl KeyModifierState {
     pub fn is_alt(&self) -> bool{ self.left_alt || self.right_alt}
     pub fn is_shift(&self) -> bool{ self.left_shift || self.right_shift}
     pub fn is_meta(&self) -> bool{ self.left_meta || self.right_meta}
+
+    /// The modifier flags of the held modifiers, the side is 'Any' if both sides are held.
+    pub fn to_flags(&self) -> KeyModifierFlags {
+        let mut flags = KeyModifierFlags::new();
+        flags.ctrl = self.is_ctrl();
+        flags.alt = self.is_alt();
+        flags.shift = self.is_shift();
+        flags.meta = self.is_meta();
+        flags.ctrl_side = ModifierSide::from_state(self.left_ctrl, self.right_ctrl);
+        flags.alt_side = ModifierSide::from_state(self.left_alt, self.right_alt);
+        flags.shift_side = ModifierSide::from_state(self.left_shift, self.right_shift);
+        flags.meta_side = ModifierSide::from_state(self.left_meta, self.right_meta);
+        flags
+    }
 }
 
 
@@ -133,3 +253,8 @@ pub struct KeyMapping {
 pub static TYPE_UP: i32 = 0;
 pub static TYPE_DOWN: i32 = 1;
 pub static TYPE_REPEAT: i32 = 2;
+
+// the values of relative event triggers, i.e. '{wheel up}' or '{wheel}' for both directions
+pub static REL_DIRECTION_ANY: i32 = 0;
+pub static REL_DIRECTION_POSITIVE: i32 = 1;
+pub static REL_DIRECTION_NEGATIVE: i32 = -1;
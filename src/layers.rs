@@ -0,0 +1,122 @@
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerActivation {
+    Toggle,
+    OneShot,
+    WhileHeld(Key),
+}
+
+/// Named mapping sets that sit on top of the global mappings.
+///
+/// Layers are looked up from the top of the stack to the bottom, the first layer that contains a
+/// mapping for the event wins. If no active layer matches, the global mappings are used.
+pub struct Layers {
+    definitions: HashMap<String, CompiledKeyMappings>,
+    stack: Vec<(String, LayerActivation)>,
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Layers { definitions: Default::default(), stack: vec![] }
+    }
+
+    pub fn mappings_mut(&mut self, name: &str) -> &mut CompiledKeyMappings {
+        self.definitions.entry(name.to_string()).or_insert_with(CompiledKeyMappings::new)
+    }
+
+    pub fn activate(&mut self, name: String, activation: LayerActivation) {
+        self.deactivate(&name);
+        self.stack.push((name, activation));
+    }
+
+    pub fn deactivate(&mut self, name: &str) {
+        self.stack.retain(|(active_name, _)| active_name != name);
+    }
+
+    pub fn toggle(&mut self, name: String) {
+        if self.is_active(&name) {
+            self.deactivate(&name);
+        } else {
+            self.activate(name, LayerActivation::Toggle);
+        }
+    }
+
+    pub fn is_active(&self, name: &str) -> bool {
+        self.stack.iter().any(|(active_name, _)| active_name == name)
+    }
+
+    /// Names of the active layers, ordered from the bottom of the stack to the top.
+    pub fn active_names(&self) -> Vec<String> {
+        self.stack.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Mappings of the active layers, ordered from the top of the stack to the bottom.
+    pub fn active_mappings(&self) -> impl Iterator<Item=&CompiledKeyMappings> {
+        self.stack.iter().rev()
+            .filter_map(move |(name, _)| self.definitions.get(name))
+    }
+
+    pub fn lookup(&self, action: &KeyActionWithMods, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>)
+                  -> Option<&CompiledKeyMapping> {
+        self.active_mappings().find_map(|mappings| mappings.get(action, window, device))
+    }
+
+    /// Updates the stack after an input event went through the mapping lookup.
+    pub fn handle_key_event(&mut self, action: &KeyActionWithMods) {
+        if action.value == TYPE_DOWN && !action.key.is_modifier() {
+            self.stack.retain(|(_, activation)| *activation != LayerActivation::OneShot);
+        } else if action.value == TYPE_UP {
+            self.stack.retain(|(_, activation)| *activation != LayerActivation::WhileHeld(action.key));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    #[test]
+    fn test_lookup_falls_through() {
+        let a_down = KeyActionWithMods::new(*KEY_A, TYPE_DOWN, KeyModifierFlags::new());
+        let b_down = KeyActionWithMods::new(*KEY_B, TYPE_DOWN, KeyModifierFlags::new());
+
+        let mut layers = Layers::new();
+        let lower = mapping();
+        let upper = mapping();
+        layers.mappings_mut("lower").insert(a_down, KeyActionCondition::new(), lower.clone());
+        layers.mappings_mut("lower").insert(b_down, KeyActionCondition::new(), lower.clone());
+        layers.mappings_mut("upper").insert(a_down, KeyActionCondition::new(), upper.clone());
+
+        assert!(layers.lookup(&a_down, None, None).is_none());
+
+        layers.activate("lower".to_string(), LayerActivation::Toggle);
+        layers.activate("upper".to_string(), LayerActivation::Toggle);
+        assert!(Arc::ptr_eq(layers.lookup(&a_down, None, None).unwrap(), &upper));
+        assert!(Arc::ptr_eq(layers.lookup(&b_down, None, None).unwrap(), &lower));
+
+        layers.toggle("upper".to_string());
+        assert!(Arc::ptr_eq(layers.lookup(&a_down, None, None).unwrap(), &lower));
+        assert_eq!(layers.active_names(), vec!["lower".to_string()]);
+    }
+
+    #[test]
+    fn test_transient_layers() {
+        let mut layers = Layers::new();
+        layers.activate("nav".to_string(), LayerActivation::WhileHeld(*KEY_CAPSLOCK));
+        layers.activate("once".to_string(), LayerActivation::OneShot);
+
+        layers.handle_key_event(&KeyActionWithMods::new(*KEY_LEFT_SHIFT, TYPE_DOWN, KeyModifierFlags::new()));
+        assert!(layers.is_active("once"));
+
+        layers.handle_key_event(&KeyActionWithMods::new(*KEY_A, TYPE_DOWN, KeyModifierFlags::new()));
+        assert!(!layers.is_active("once"));
+        assert!(layers.is_active("nav"));
+
+        layers.handle_key_event(&KeyActionWithMods::new(*KEY_CAPSLOCK, TYPE_UP, KeyModifierFlags::new()));
+        assert!(!layers.is_active("nav"));
+    }
+}
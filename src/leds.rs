@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use evdev_rs::enums::EventType;
+
+use crate::*;
+
+/// Gets an LED by the name of its lock key (i.e. 'capslock') or by its event name (i.e. 'led_mute').
+pub fn parse_led(name: &str) -> Result<Key> {
+    let name = name.to_uppercase();
+    let name = match &*name {
+        "CAPSLOCK" => "LED_CAPSL".to_string(),
+        "NUMLOCK" => "LED_NUML".to_string(),
+        "SCROLLLOCK" => "LED_SCROLLL".to_string(),
+        "COMPOSE" => "LED_COMPOSE".to_string(),
+        "KANA" => "LED_KANA".to_string(),
+        _ => name,
+    };
+    Key::from_str(&EventType::EV_LED, &name)
+        .map_err(|_| anyhow!("unknown LED '{}'", name))
+}
+
+/// Keeps track of the lock state and the LEDs of grabbed keyboards.
+///
+/// The lock state is reported by the system through the virtual output device and mirrored onto the LEDs,
+/// unless an LED is set by the script or lit by an active layer.
+pub struct Leds {
+    lock_state: HashSet<Key>,
+    overrides: HashMap<Key, bool>,
+    // LEDs that are lit while a layer is active
+    layer_leds: Vec<(String, Key)>,
+    active_layers: Vec<String>,
+    // the LED values last written to the keyboards
+    written: HashMap<Key, bool>,
+}
+
+impl Leds {
+    pub fn new() -> Self {
+        Leds {
+            lock_state: HashSet::new(),
+            overrides: HashMap::new(),
+            layer_leds: vec![],
+            active_layers: vec![],
+            written: HashMap::new(),
+        }
+    }
+
+    pub fn is_locked(&self, led: &Key) -> bool { self.lock_state.contains(led) }
+
+    /// Records a change of the lock state, gets the events that update the LEDs.
+    pub fn set_lock_state(&mut self, led: Key, locked: bool) -> Vec<InputEvent> {
+        if locked { self.lock_state.insert(led); } else { self.lock_state.remove(&led); }
+        self.update(led)
+    }
+
+    /// Sets an LED regardless of the lock state, the LED follows the lock state again if no value is given.
+    pub fn set_override(&mut self, led: Key, value: Option<bool>) -> Vec<InputEvent> {
+        match value {
+            Some(value) => { self.overrides.insert(led, value); }
+            None => { self.overrides.remove(&led); }
+        }
+        self.update(led)
+    }
+
+    pub fn insert_layer_led(&mut self, layer: String, led: Key) -> Vec<InputEvent> {
+        self.layer_leds.push((layer, led));
+        self.update(led)
+    }
+
+    /// Gets the events that update the LEDs of layers that were activated or deactivated.
+    pub fn set_active_layers(&mut self, active_layers: Vec<String>) -> Vec<InputEvent> {
+        if self.active_layers == active_layers { return vec![]; }
+        self.active_layers = active_layers;
+
+        let leds = self.layer_leds.iter().map(|(_, led)| *led).collect::<Vec<_>>();
+        leds.into_iter().flat_map(|led| self.update(led)).collect()
+    }
+
+    fn value(&self, led: &Key) -> bool {
+        if let Some(value) = self.overrides.get(led) { return *value; }
+        let layer_active = self.layer_leds.iter()
+            .any(|(layer, layer_led)| layer_led == led && self.active_layers.contains(layer));
+        layer_active || self.is_locked(led)
+    }
+
+    fn update(&mut self, led: Key) -> Vec<InputEvent> {
+        let value = self.value(&led);
+        if self.written.get(&led) == Some(&value) { return vec![]; }
+        self.written.insert(led, value);
+        vec![KeyAction::new(led, value as i32).to_input_ev()]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_led() {
+        assert_eq!(parse_led("capslock").unwrap(), *LED_CAPSL);
+        assert_eq!(parse_led("LED_CAPSL").unwrap(), *LED_CAPSL);
+        assert!(parse_led("shiftlock").is_err());
+    }
+
+    #[test]
+    fn test_lock_state() {
+        let mut leds = Leds::new();
+        assert_eq!(leds.set_lock_state(*LED_CAPSL, true), vec![ev(*LED_CAPSL, 1)]);
+        assert!(leds.is_locked(&LED_CAPSL));
+        assert_eq!(leds.set_lock_state(*LED_CAPSL, true), vec![]);
+
+        // overridden LEDs don't follow the lock state
+        assert_eq!(leds.set_override(*LED_CAPSL, Some(false)), vec![ev(*LED_CAPSL, 0)]);
+        assert_eq!(leds.set_lock_state(*LED_CAPSL, false), vec![]);
+        assert_eq!(leds.set_lock_state(*LED_CAPSL, true), vec![]);
+        assert_eq!(leds.set_override(*LED_CAPSL, None), vec![ev(*LED_CAPSL, 1)]);
+    }
+
+    #[test]
+    fn test_layer_leds() {
+        let mut leds = Leds::new();
+        assert_eq!(leds.insert_layer_led("navigation".to_string(), *LED_SCROLLL), vec![ev(*LED_SCROLLL, 0)]);
+
+        assert_eq!(leds.set_active_layers(vec!["navigation".to_string()]), vec![ev(*LED_SCROLLL, 1)]);
+        assert_eq!(leds.set_active_layers(vec!["navigation".to_string()]), vec![]);
+        assert_eq!(leds.set_active_layers(vec![]), vec![ev(*LED_SCROLLL, 0)]);
+    }
+}
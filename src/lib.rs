@@ -18,11 +18,29 @@ pub use tokio::prelude::*;
 pub use tokio::sync::{mpsc, oneshot};
 pub use tokio::task;
 
+pub use crate::abs_thresholds::*;
+pub use crate::auto_shift::*;
 pub use crate::cli::parse_cli;
-pub use crate::device::virtual_input_device::bind_udev_inputs;
+pub use crate::executor::*;
+pub use crate::gamepad::*;
+pub use crate::gestures::*;
+pub use crate::debounce::*;
+pub use crate::device::virtual_input_device::{bind_udev_inputs, DeviceInfo, DeviceInputEvent};
+pub use crate::device::virtual_output_device::{OutputEvent, OutputTarget};
+pub use crate::joystick_mouse::*;
 pub use crate::key_defs::*;
 pub use crate::key_primitives::*;
+pub use crate::layers::*;
+pub use crate::leds::*;
+pub use crate::macros::*;
+pub use crate::mouse::*;
+pub use crate::mouse_keys::*;
+pub use crate::pointer_transform::*;
+pub use crate::sticky_modifiers::*;
+pub use crate::tap_dance::*;
+pub use crate::turbo::*;
 pub use crate::runtime::*;
+pub use crate::scroll_emulation::*;
 pub use crate::runtime::evaluation::*;
 pub use crate::state::*;
 pub use crate::x11::{x11_initialize, x11_test};
@@ -42,6 +60,23 @@ pub mod ignore_list;
 pub mod messaging;
 pub mod event_handlers;
 pub mod logging;
+pub mod layers;
+pub mod sticky_modifiers;
+pub mod executor;
+pub mod tap_dance;
+pub mod auto_shift;
+pub mod macros;
+pub mod turbo;
+pub mod debounce;
+pub mod mouse;
+pub mod mouse_keys;
+pub mod scroll_emulation;
+pub mod pointer_transform;
+pub mod gestures;
+pub mod abs_thresholds;
+pub mod gamepad;
+pub mod joystick_mouse;
+pub mod leds;
 
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
+
+use evdev_rs::enums::{EventType, int_to_event_type};
+use evdev_rs::util::event_code_to_int;
+use xdg::BaseDirectories;
+
+use crate::*;
+
+/// Recorded input events, each along with the time passed since the previous event.
+pub type Macro = Vec<(time::Duration, InputEvent)>;
+
+struct Recording {
+    name: String,
+    events: Macro,
+    last_event: time::Instant,
+    // keys pressed during the recording that weren't released yet
+    pressed: HashSet<Key>,
+}
+
+/// Input event sequences that are recorded at runtime and can be replayed later.
+pub struct Macros {
+    macros: HashMap<String, Arc<Macro>>,
+    recording: Option<Recording>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Macros { macros: Default::default(), recording: None }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Macro>> { self.macros.get(name).cloned() }
+
+    pub fn insert(&mut self, name: String, events: Arc<Macro>) { self.macros.insert(name, events); }
+
+    /// Starts recording the input events, an active recording is stopped first.
+    pub fn start_recording(&mut self, name: String) {
+        self.stop_recording(None);
+        self.recording = Some(Recording {
+            name,
+            events: vec![],
+            last_event: time::Instant::now(),
+            pressed: Default::default(),
+        });
+    }
+
+    /// Stores the active recording, the events of the key that stopped it and of keys that are still held
+    /// are left out.
+    pub fn stop_recording(&mut self, trigger_key: Option<Key>) {
+        let recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        let mut events = vec![];
+        let mut skipped_delay = time::Duration::from_millis(0);
+        let mut skip_syn = false;
+        for (delay, ev) in recording.events {
+            let key = Key { event_code: ev.event_code };
+            let skip = match ev.event_code {
+                EventCode::EV_KEY(_) => Some(key) == trigger_key || recording.pressed.contains(&key),
+                EventCode::EV_SYN(_) => skip_syn,
+                _ => false,
+            };
+            skip_syn = skip;
+
+            if skip {
+                skipped_delay += delay;
+            } else {
+                events.push((delay + skipped_delay, ev));
+                skipped_delay = time::Duration::from_millis(0);
+            }
+        }
+
+        // the macro starts right away when it's played
+        if let Some(first) = events.first_mut() { first.0 = time::Duration::from_millis(0); }
+
+        self.macros.insert(recording.name, Arc::new(events));
+    }
+
+    pub fn record(&mut self, ev: &InputEvent) {
+        let recording = match &mut self.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        if let EventCode::EV_KEY(_) = ev.event_code {
+            let key = Key { event_code: ev.event_code };
+            if ev.value == TYPE_DOWN {
+                recording.pressed.insert(key);
+            } else if !recording.pressed.contains(&key) {
+                // the key was pressed before the recording started
+                return;
+            } else if ev.value == TYPE_UP {
+                recording.pressed.remove(&key);
+            }
+        }
+
+        let now = time::Instant::now();
+        recording.events.push((now - recording.last_event, ev.clone()));
+        recording.last_event = now;
+    }
+}
+
+fn macro_file_name(name: &str) -> Result<String> {
+    if name.is_empty() || name.contains('/') { return Err(anyhow!("invalid macro name '{}'", name)); }
+    Ok(format!("macros/{}", name))
+}
+
+/// Writes a macro to the XDG data directory, each line holds the delay in milliseconds, the event type, the
+/// event code and the value.
+pub fn save_macro(name: &str, events: &Macro) -> Result<()> {
+    let xdg_dirs = BaseDirectories::with_prefix("map2")
+        .map_err(|_| anyhow!("failed to initialize XDG directory configuration"))?;
+    let path = xdg_dirs.place_data_file(macro_file_name(name)?)
+        .map_err(|err| anyhow!("failed to create the macro directory: {}", err))?;
+
+    let mut content = String::new();
+    for (delay, ev) in events {
+        let (event_type, _) = event_code_to_int(&ev.event_code);
+        let event_type = int_to_event_type(event_type).ok_or_else(|| anyhow!("invalid event type"))?;
+        content.push_str(&format!("{} {} {} {}\n", delay.as_millis(), event_type, ev.event_code, ev.value));
+    }
+
+    fs::write(&path, content).map_err(|err| anyhow!("failed to write macro '{}': {}", path.display(), err))
+}
+
+/// Reads a macro that was written with `save_macro`.
+pub fn load_macro(name: &str) -> Result<Macro> {
+    let xdg_dirs = BaseDirectories::with_prefix("map2")
+        .map_err(|_| anyhow!("failed to initialize XDG directory configuration"))?;
+    let path = xdg_dirs.find_data_file(macro_file_name(name)?)
+        .ok_or_else(|| anyhow!("macro '{}' not found", name))?;
+    let file = fs::File::open(&path).map_err(|err| anyhow!("failed to read macro '{}': {}", path.display(), err))?;
+
+    let mut events = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| anyhow!("failed to read macro '{}': {}", path.display(), err))?;
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        let event = match parts.as_slice() {
+            [delay, event_type, event_code, value] => {
+                EventType::from_str(event_type)
+                    .and_then(|event_type| EventCode::from_str(&event_type, event_code))
+                    .and_then(|event_code| Some((delay.parse().ok()?, event_code, value.parse().ok()?)))
+            }
+            _ => None,
+        };
+        let (delay, event_code, value) = event.ok_or_else(|| anyhow!("invalid macro line '{}'", line))?;
+
+        events.push((time::Duration::from_millis(delay), InputEvent { event_code, value, time: INPUT_EV_DUMMY_TIME }));
+    }
+    Ok(events)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn recorded_events(macros: &Macros, name: &str) -> Vec<InputEvent> {
+        macros.get(name).unwrap().iter().map(|(_, ev)| ev.clone()).collect()
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut macros = Macros::new();
+        macros.record(&ev(*KEY_A, TYPE_DOWN));
+
+        macros.start_recording("test".to_string());
+        // released keys that were pressed before the recording are skipped
+        macros.record(&ev(*KEY_A, TYPE_UP));
+        macros.record(&ev(*KEY_B, TYPE_DOWN));
+        macros.record(&SYN_REPORT.clone());
+        macros.record(&ev(*KEY_B, TYPE_UP));
+        // the key that stops the recording is skipped
+        macros.record(&ev(*KEY_F2, TYPE_DOWN));
+        macros.record(&SYN_REPORT.clone());
+        macros.record(&ev(*KEY_F2, TYPE_UP));
+        // as are keys that are still held
+        macros.record(&ev(*KEY_C, TYPE_DOWN));
+        macros.stop_recording(Some(*KEY_F2));
+
+        assert_eq!(recorded_events(&macros, "test"), vec![
+            ev(*KEY_B, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*KEY_B, TYPE_UP),
+        ]);
+
+        macros.record(&ev(*KEY_D, TYPE_DOWN));
+        assert_eq!(macros.get("test").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_macro_names() {
+        assert!(macro_file_name("test").is_ok());
+        assert!(macro_file_name("../test").is_err());
+        assert!(macro_file_name("").is_err());
+    }
+}
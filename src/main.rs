@@ -47,7 +47,8 @@ async fn main() -> Result<()> {
     let (ev_writer_tx, mut ev_writer_rx) = mpsc::channel(128);
 
     // send one end of the communication channels to the readers/writer
-    bind_udev_inputs(&configuration.devices, ev_reader_init_tx, ev_writer_tx).await?;
+    bind_udev_inputs(&configuration.devices, configuration.gamepad, execution_message_tx.clone(),
+                     ev_reader_init_tx, ev_writer_tx).await?;
     let mut ev_reader_tx = ev_reader_init_rx.await?;
 
     // initial evaluation pass on global scope
@@ -67,9 +68,9 @@ async fn main() -> Result<()> {
                 event_handlers::handle_active_window_change(&mut ev_reader_tx,
                     &mut execution_message_tx, window_cycle_token, &mut window_change_handlers);
             }
-            Some(ev) = ev_writer_rx.recv() => {
+            Some((device, ev)) = ev_writer_rx.recv() => {
                 event_handlers::handle_stdin_ev(
-                    &mut state, ev,
+                    &mut state, ev, &device,
                     &mut mappings,
                     &mut ev_reader_tx,
                     &mut execution_message_tx,
@@ -79,7 +80,7 @@ async fn main() -> Result<()> {
             }
             Some(msg) = message_rx.recv() => {
                 event_handlers::handle_execution_message(&mut stdout, window_cycle_token, msg, &mut state,
-                    &mut mappings, &mut window_change_handlers).await;
+                    &mut mappings, &mut ev_reader_tx, &mut execution_message_tx, &mut window_change_handlers).await;
             }
         }
     }
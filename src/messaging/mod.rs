@@ -5,7 +5,58 @@ use crate::*;
 #[derive(Debug)]
 pub enum ExecutionMessage {
     // EatEv(KeyAction),
-    AddMapping(usize, KeyActionWithMods, Block, GuardedVarMap),
+    AddMapping(usize, Option<String>, KeyActionCondition, KeyActionWithMods, Block, GuardedVarMap, MappingOptions),
+    ActivateLayer(String, LayerActivation),
+    DeactivateLayer(String),
+    ToggleLayer(String),
+    GetActiveLayers(mpsc::Sender<Vec<String>>),
+    SetStickyModifiers(KeyModifierFlags),
+    SetCapsWord(bool),
+    AddTapDance(Key, TapDanceAction, Block, GuardedVarMap),
+    SetTapDanceTimeout(u64),
+    TapDanceTimeout(usize),
+    SetAutoShift(bool),
+    SetAutoShiftTimeout(u64),
+    ExcludeFromAutoShift(Vec<Key>),
+    AutoShiftTimeout(usize),
+    StartMacroRecording(String),
+    StopMacroRecording(Option<Key>),
+    GetMacro(String, mpsc::Sender<Option<Arc<Macro>>>),
+    SetMacro(String, Arc<Macro>),
+    PlayMacroEvent(Arc<DeviceInfo>, InputEvent),
+    StartTurbo(String, Vec<KeyAction>, time::Duration, time::Duration),
+    ToggleTurbo(String, Vec<KeyAction>, time::Duration, time::Duration),
+    StopTurbo(Option<String>),
+    SetDebounce(KeyActionCondition, Vec<Key>, time::Duration),
+    GetDebounceCount(mpsc::Sender<usize>),
+    Scroll(f64, f64, mpsc::Sender<Vec<InputEvent>>),
+    MouseKeysPress(MouseKeysAction, KeyPressInvocation),
+    MouseKeysTick,
+    MouseKeysDrag(Key),
+    SetMouseKeysSpeed(f64, f64),
+    SetMouseKeysAcceleration(time::Duration, f64),
+    SetMouseKeysScrollRate(f64),
+    SetScrollEmulation(Option<Key>),
+    SetScrollEmulationSensitivity(f64),
+    SetScrollEmulationAxisLock(bool),
+    SetScrollEmulationNatural(bool),
+    SetPointerTransform(KeyActionCondition, PointerSetting),
+    AddGesture(Vec<GestureDirection>, Block, GuardedVarMap),
+    SetGestureTrigger(Key),
+    SetGestureThreshold(i32),
+    AddAbsThreshold(Key, f64, Key),
+    SetGamepadDeadzone(Stick, f64),
+    SetGamepadCurve(Stick, f64),
+    SetGamepadSwapSticks(bool),
+    AddGamepadKeyAxis(Key, Key, f64),
+    SetJoystickMouse(Stick, Option<JoystickMouseMode>, f64),
+    SetJoystickMouseDeadzone(Stick, f64),
+    SetJoystickMouseAcceleration(Stick, f64),
+    JoystickMouseTick,
+    LockStateChanged(Key, bool),
+    GetLockState(Key, mpsc::Sender<bool>),
+    SetLed(Key, Option<bool>),
+    AddLayerLed(String, Key),
     GetFocusedWindowInfo(mpsc::Sender<Option<ActiveWindowInfo>>),
     RegisterWindowChangeCallback(Block, GuardedVarMap),
     Write(String),
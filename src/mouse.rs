@@ -0,0 +1,273 @@
+use tokio::sync::watch;
+
+use crate::*;
+use crate::messaging::{ExecutionMessage, ExecutionMessageSender};
+use crate::parsing::parser::parse_key;
+
+/// The distance of a single wheel notch in high resolution wheel events.
+pub const WHEEL_HI_RES_UNITS: f64 = 120.0;
+
+/// The interval in which continuous pointer movement (i.e. held mouse keys or pushed sticks) is updated.
+pub const POINTER_TICK: time::Duration = time::Duration::from_millis(16);
+
+fn rel_ev(axis: Key, value: i32) -> InputEvent { KeyAction::new(axis, value).to_input_ev() }
+
+/// A frame that moves the pointer by the given distance.
+pub fn move_events(dx: i32, dy: i32) -> Vec<InputEvent> {
+    let mut events = vec![];
+    if dx != 0 { events.push(rel_ev(*REL_X, dx)); }
+    if dy != 0 { events.push(rel_ev(*REL_Y, dy)); }
+    if !events.is_empty() { events.push(SYN_REPORT.clone()); }
+    events
+}
+
+/// Turns continuous scrolling into wheel events, high resolution events are sent right away while regular
+/// events are sent once a whole notch was scrolled.
+#[derive(Default)]
+pub struct WheelAccumulator {
+    hi_res_remainder: f64,
+    notch_remainder: i32,
+}
+
+impl WheelAccumulator {
+    pub fn new() -> Self { Default::default() }
+
+    /// Scrolls by the given number of high resolution units, the events aren't terminated by a SYN_REPORT.
+    pub fn scroll(&mut self, axis: Key, hi_res_axis: Key, units: f64) -> Vec<InputEvent> {
+        self.hi_res_remainder += units;
+        let hi_res = self.hi_res_remainder.trunc();
+        self.hi_res_remainder -= hi_res;
+        let hi_res = hi_res as i32;
+        if hi_res == 0 { return vec![]; }
+
+        let mut events = vec![rel_ev(hi_res_axis, hi_res)];
+        self.notch_remainder += hi_res;
+        let notches = self.notch_remainder / WHEEL_HI_RES_UNITS as i32;
+        if notches != 0 {
+            self.notch_remainder -= notches * WHEEL_HI_RES_UNITS as i32;
+            events.push(rel_ev(axis, notches));
+        }
+        events
+    }
+}
+
+/// The wheels scrolled by the 'scroll' function.
+///
+/// Fractions of a notch are sent as high resolution wheel events right away and carried over, so repeated
+/// small scrolls add up to regular wheel events for applications without high resolution scrolling.
+#[derive(Default)]
+pub struct Wheels {
+    vertical: WheelAccumulator,
+    horizontal: WheelAccumulator,
+}
+
+impl Wheels {
+    pub fn new() -> Self { Default::default() }
+
+    /// A frame that scrolls by the given number of wheel notches, positive values scroll up and right.
+    pub fn scroll(&mut self, vertical: f64, horizontal: f64) -> Vec<InputEvent> {
+        let mut events = self.vertical.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, vertical * WHEEL_HI_RES_UNITS);
+        events.extend(self.horizontal.scroll(*REL_HWHEEL, *REL_HWHEEL_HI_RES, horizontal * WHEEL_HI_RES_UNITS));
+        if !events.is_empty() { events.push(SYN_REPORT.clone()); }
+        events
+    }
+}
+
+/// Gets the regular wheel axis of a high resolution wheel axis.
+pub fn hi_res_wheel(key: Key) -> Option<Key> {
+    if key == *REL_WHEEL_HI_RES { return Some(*REL_WHEEL); }
+    if key == *REL_HWHEEL_HI_RES { return Some(*REL_HWHEEL); }
+    None
+}
+
+/// Counts the notches scrolled on mapped wheels of devices that report high resolution wheel events.
+///
+/// Such devices may scroll by fractions of a notch, the mappings are triggered once a whole notch was scrolled.
+/// The regular wheel events of these devices duplicate the high resolution ones and are dropped.
+#[derive(Default)]
+pub struct WheelNotches {
+    // the fraction of a notch scrolled by the path of the device and the regular wheel axis
+    remainders: HashMap<(String, Key), i32>,
+}
+
+impl WheelNotches {
+    pub fn new() -> Self { Default::default() }
+
+    /// Adds the high resolution units to the wheel of the device, gets the whole notches scrolled.
+    pub fn scroll(&mut self, device: &str, wheel: Key, units: i32) -> i32 {
+        let remainder = self.remainders.entry((device.to_string(), wheel)).or_default();
+        // turning the wheel around drops the fraction scrolled in the other direction
+        if remainder.signum() * units.signum() < 0 { *remainder = 0; }
+        *remainder += units;
+        let notches = *remainder / WHEEL_HI_RES_UNITS as i32;
+        *remainder -= notches * WHEEL_HI_RES_UNITS as i32;
+        notches
+    }
+
+    /// Whether the device reported high resolution events for the wheel.
+    pub fn is_hi_res(&self, device: &str, wheel: Key) -> bool {
+        self.remainders.contains_key(&(device.to_string(), wheel))
+    }
+}
+
+/// Moves the pointer continuously, a message is sent every tick while active.
+///
+/// Sub-pixel movement is carried over to the next tick, so slow movement still adds up.
+pub struct PointerTicker {
+    pub(crate) last_tick: time::Instant,
+    remainder: (f64, f64),
+    // dropping the sender stops the ticks
+    ticker: Option<watch::Sender<bool>>,
+}
+
+impl PointerTicker {
+    pub fn new() -> Self {
+        PointerTicker { last_tick: time::Instant::now(), remainder: (0.0, 0.0), ticker: None }
+    }
+
+    pub fn is_active(&self) -> bool { self.ticker.is_some() }
+
+    /// Sends a message every tick until stopped, does nothing if the ticks already run.
+    pub fn start(&mut self, message_tx: &ExecutionMessageSender, message: fn() -> ExecutionMessage) {
+        if self.is_active() { return; }
+        self.last_tick = time::Instant::now();
+
+        let (stop_tx, mut stop_rx) = watch::channel(false);
+        let message_tx = message_tx.clone();
+        task::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(POINTER_TICK) => {}
+                    _ = stop_rx.changed() => break,
+                }
+                if message_tx.send(message()).await.is_err() { break; }
+            }
+        });
+        self.ticker = Some(stop_tx);
+    }
+
+    pub fn stop(&mut self) {
+        self.ticker = None;
+        self.remainder = (0.0, 0.0);
+    }
+
+    /// The seconds passed since the last tick.
+    pub fn elapsed(&mut self, now: time::Instant) -> f64 {
+        // a delayed tick must not make the pointer jump
+        let dt = now.duration_since(self.last_tick).min(POINTER_TICK * 4).as_secs_f64();
+        self.last_tick = now;
+        dt
+    }
+
+    /// Moves the pointer by the whole pixels of the given and the carried over movement, the events aren't
+    /// terminated by a SYN_REPORT.
+    pub fn move_events(&mut self, dx: f64, dy: f64) -> Vec<InputEvent> {
+        let dx = take_whole(&mut self.remainder.0, dx);
+        let dy = take_whole(&mut self.remainder.1, dy);
+        let mut events = vec![];
+        if dx != 0 { events.push(rel_ev(*REL_X, dx)); }
+        if dy != 0 { events.push(rel_ev(*REL_Y, dy)); }
+        events
+    }
+}
+
+// adds the value to the remainder and takes the whole part out of it
+fn take_whole(remainder: &mut f64, value: f64) -> i32 {
+    *remainder += value;
+    let whole = remainder.trunc();
+    *remainder -= whole;
+    whole as i32
+}
+
+/// Gets a mouse button by its short name (i.e. 'left') or by its key name (i.e. 'btn_forward').
+pub fn parse_button(name: &str) -> Result<Key> {
+    match &*name.to_lowercase() {
+        "left" => Ok(*BTN_LEFT),
+        "right" => Ok(*BTN_RIGHT),
+        "middle" => Ok(*BTN_MIDDLE),
+        "side" => Ok(*BTN_SIDE),
+        "extra" => Ok(*BTN_EXTRA),
+        _ => parse_key(name).ok()
+            .filter(|key| key.event_code.to_string().starts_with("BTN_"))
+            .ok_or_else(|| anyhow!("unknown mouse button '{}'", name)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_events() {
+        let mut wheels = Wheels::new();
+        assert_eq!(wheels.scroll(1.0, -2.0), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, 120).to_input_ev(),
+            KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+            KeyAction::new(*REL_HWHEEL_HI_RES, -240).to_input_ev(),
+            KeyAction::new(*REL_HWHEEL, -2).to_input_ev(),
+            SYN_REPORT.clone(),
+        ]);
+
+        // fractions of a notch add up to a regular wheel event
+        assert_eq!(wheels.scroll(-0.5, 0.0), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, -60).to_input_ev(),
+            SYN_REPORT.clone(),
+        ]);
+        assert_eq!(wheels.scroll(-0.5, 0.0), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, -60).to_input_ev(),
+            KeyAction::new(*REL_WHEEL, -1).to_input_ev(),
+            SYN_REPORT.clone(),
+        ]);
+
+        assert_eq!(wheels.scroll(0.0, 0.0), vec![]);
+        assert_eq!(move_events(0, 0), vec![]);
+    }
+
+    #[test]
+    fn test_wheel_accumulator() {
+        let mut wheel = WheelAccumulator::new();
+        assert_eq!(wheel.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, 0.5), vec![]);
+        assert_eq!(wheel.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, 60.5), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, 61).to_input_ev(),
+        ]);
+        assert_eq!(wheel.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, 60.0), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, 60).to_input_ev(),
+            KeyAction::new(*REL_WHEEL, 1).to_input_ev(),
+        ]);
+        assert_eq!(wheel.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, -2.0), vec![
+            KeyAction::new(*REL_WHEEL_HI_RES, -2).to_input_ev(),
+        ]);
+    }
+
+    #[test]
+    fn test_remainder() {
+        let mut remainder = 0.0;
+        assert_eq!(take_whole(&mut remainder, 0.6), 0);
+        assert_eq!(take_whole(&mut remainder, 0.6), 1);
+        assert_eq!(take_whole(&mut remainder, -1.7), -1);
+    }
+
+    #[test]
+    fn test_wheel_notches() {
+        let mut notches = WheelNotches::new();
+        assert!(!notches.is_hi_res("/dev/input/event0", *REL_WHEEL));
+        assert_eq!(notches.scroll("/dev/input/event0", *REL_WHEEL, 60), 0);
+        assert!(notches.is_hi_res("/dev/input/event0", *REL_WHEEL));
+        assert!(!notches.is_hi_res("/dev/input/event0", *REL_HWHEEL));
+        assert_eq!(notches.scroll("/dev/input/event0", *REL_WHEEL, 90), 1);
+        assert_eq!(notches.scroll("/dev/input/event0", *REL_WHEEL, 240), 2);
+
+        // the fraction scrolled up doesn't count towards scrolling down
+        assert_eq!(notches.scroll("/dev/input/event0", *REL_WHEEL, -100), 0);
+        assert_eq!(notches.scroll("/dev/input/event0", *REL_WHEEL, -20), -1);
+    }
+
+    #[test]
+    fn test_parse_button() {
+        assert_eq!(parse_button("left").unwrap(), *BTN_LEFT);
+        assert_eq!(parse_button("BTN_EXTRA").unwrap(), *BTN_EXTRA);
+        assert!(parse_button("not a button").is_err());
+        assert!(parse_button("a").is_err());
+    }
+}
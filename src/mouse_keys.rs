@@ -0,0 +1,193 @@
+use crate::*;
+use crate::messaging::{ExecutionMessage, ExecutionMessageSender};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MouseKeysAction {
+    /// moves the pointer in the given direction, each axis is either -1, 0 or 1
+    Move(i32, i32),
+    /// scrolls in the given direction, positive values scroll up and right
+    Scroll(i32, i32),
+}
+
+/// Moves the pointer or scrolls while keys are held.
+///
+/// The pointer starts at the initial speed and accelerates to the max speed (in pixels per second) within the
+/// acceleration time, the exponent shapes the curve in between (1 is linear). Held keys are polled on every
+/// tick, so the movement stops once all of them are released or the active layers change.
+pub struct MouseKeys {
+    initial_speed: f64,
+    max_speed: f64,
+    acceleration_time: time::Duration,
+    acceleration_exponent: f64,
+    // wheel notches per second
+    scroll_rate: f64,
+    held: Vec<(MouseKeysAction, KeyPressInvocation)>,
+    // the active layers when the movement started
+    layers: Vec<String>,
+    started: time::Instant,
+    ticker: PointerTicker,
+    // partial wheel notches carried over to the next tick
+    wheel: (WheelAccumulator, WheelAccumulator),
+    // the button held down by drag lock
+    dragged: Option<Key>,
+}
+
+impl MouseKeys {
+    pub fn new() -> Self {
+        MouseKeys {
+            initial_speed: 100.0,
+            max_speed: 1200.0,
+            acceleration_time: time::Duration::from_millis(800),
+            acceleration_exponent: 2.0,
+            scroll_rate: 10.0,
+            held: vec![],
+            layers: vec![],
+            started: time::Instant::now(),
+            ticker: PointerTicker::new(),
+            wheel: Default::default(),
+            dragged: None,
+        }
+    }
+
+    pub fn set_speed(&mut self, initial_speed: f64, max_speed: f64) {
+        self.initial_speed = initial_speed;
+        self.max_speed = max_speed.max(initial_speed);
+    }
+
+    pub fn set_acceleration(&mut self, acceleration_time: time::Duration, acceleration_exponent: f64) {
+        self.acceleration_time = acceleration_time;
+        self.acceleration_exponent = acceleration_exponent;
+    }
+
+    pub fn set_scroll_rate(&mut self, scroll_rate: f64) { self.scroll_rate = scroll_rate; }
+
+    pub fn is_active(&self) -> bool { self.ticker.is_active() }
+
+    /// Starts an action that lasts until the key press of the invocation is released.
+    pub fn press(&mut self, action: MouseKeysAction, invocation: KeyPressInvocation, layers: Vec<String>,
+                 message_tx: &ExecutionMessageSender) {
+        if !self.is_active() {
+            self.started = time::Instant::now();
+            self.layers = layers;
+            self.ticker.start(message_tx, || ExecutionMessage::MouseKeysTick);
+        }
+        self.held.push((action, invocation));
+    }
+
+    /// Stops all actions, a held drag lock button stays pressed.
+    pub fn stop(&mut self) {
+        self.held.clear();
+        self.ticker.stop();
+        self.wheel = Default::default();
+    }
+
+    /// Presses the button until it's toggled again, a different button that is held gets released first.
+    pub fn toggle_drag(&mut self, button: Key) -> Vec<InputEvent> {
+        let mut events = vec![];
+        if let Some(dragged) = self.dragged.take() {
+            events.push(KeyAction::new(dragged, TYPE_UP).to_input_ev());
+            events.push(SYN_REPORT.clone());
+            if dragged == button { return events; }
+        }
+
+        self.dragged = Some(button);
+        events.push(KeyAction::new(button, TYPE_DOWN).to_input_ev());
+        events.push(SYN_REPORT.clone());
+        events
+    }
+
+    /// The pointer speed in pixels per second after the given time of movement.
+    fn speed(&self, elapsed: time::Duration) -> f64 {
+        if self.acceleration_time.as_nanos() == 0 { return self.max_speed; }
+        let progress = (elapsed.as_secs_f64() / self.acceleration_time.as_secs_f64()).min(1.0);
+        self.initial_speed + (self.max_speed - self.initial_speed) * progress.powf(self.acceleration_exponent)
+    }
+
+    /// Gets the events for the time passed since the last tick.
+    pub fn tick(&mut self, now: time::Instant, layers: &[String]) -> Vec<InputEvent> {
+        self.held.retain(|(_, invocation)| !invocation.is_released());
+        if self.held.is_empty() || self.layers != layers {
+            self.stop();
+            return vec![];
+        }
+
+        let dt = self.ticker.elapsed(now);
+
+        let (mut move_dir, mut scroll_dir) = ((0, 0), (0, 0));
+        for (action, _) in self.held.iter() {
+            match action {
+                MouseKeysAction::Move(x, y) => move_dir = (move_dir.0 + x, move_dir.1 + y),
+                MouseKeysAction::Scroll(v, h) => scroll_dir = (scroll_dir.0 + v, scroll_dir.1 + h),
+            }
+        }
+
+        let distance = self.speed(now.duration_since(self.started)) * dt;
+        let mut events = self.ticker.move_events(move_dir.0.signum() as f64 * distance,
+                                                 move_dir.1.signum() as f64 * distance);
+
+        let units = self.scroll_rate * WHEEL_HI_RES_UNITS * dt;
+        events.extend(self.wheel.0.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, scroll_dir.0.signum() as f64 * units));
+        events.extend(self.wheel.1.scroll(*REL_HWHEEL, *REL_HWHEEL_HI_RES, scroll_dir.1.signum() as f64 * units));
+
+        if !events.is_empty() { events.push(SYN_REPORT.clone()); }
+        events
+    }
+}
+
+/// Parses space separated directions, i.e. 'up left', into the movement on each axis. Positive values point
+/// right and down.
+pub fn parse_direction(raw: &str) -> Result<(i32, i32)> {
+    let mut direction = (0, 0);
+    for word in raw.split_whitespace() {
+        match word {
+            "up" => direction.1 = -1,
+            "down" => direction.1 = 1,
+            "left" => direction.0 = -1,
+            "right" => direction.0 = 1,
+            _ => return Err(anyhow!("invalid direction '{}'", word)),
+        }
+    }
+    if direction == (0, 0) { return Err(anyhow!("no direction given")); }
+    Ok(direction)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acceleration() {
+        let mut mouse_keys = MouseKeys::new();
+        mouse_keys.set_speed(100.0, 1100.0);
+        mouse_keys.set_acceleration(time::Duration::from_millis(1000), 1.0);
+
+        assert_eq!(mouse_keys.speed(time::Duration::from_millis(0)), 100.0);
+        assert_eq!(mouse_keys.speed(time::Duration::from_millis(500)), 600.0);
+        assert_eq!(mouse_keys.speed(time::Duration::from_millis(5000)), 1100.0);
+
+        mouse_keys.set_acceleration(time::Duration::from_millis(1000), 2.0);
+        assert_eq!(mouse_keys.speed(time::Duration::from_millis(500)), 350.0);
+    }
+
+    #[test]
+    fn test_parse_direction() {
+        assert_eq!(parse_direction("up left").unwrap(), (-1, -1));
+        assert_eq!(parse_direction("down").unwrap(), (0, 1));
+        assert!(parse_direction("").is_err());
+        assert!(parse_direction("forward").is_err());
+    }
+
+    #[test]
+    fn test_drag_lock() {
+        let mut mouse_keys = MouseKeys::new();
+        assert_eq!(mouse_keys.toggle_drag(*BTN_LEFT), vec![
+            KeyAction::new(*BTN_LEFT, TYPE_DOWN).to_input_ev(),
+            SYN_REPORT.clone(),
+        ]);
+        assert_eq!(mouse_keys.toggle_drag(*BTN_LEFT), vec![
+            KeyAction::new(*BTN_LEFT, TYPE_UP).to_input_ev(),
+            SYN_REPORT.clone(),
+        ]);
+    }
+}
@@ -10,7 +10,7 @@ pub(super) fn ident(input: &str) -> ResNew<&str, String> {
 
     match id.0.as_ref() {
         "break" | "continue" | "do" | "else" | "false" | "for" |
-        "if" | "in" | "let" | "loop" | "return" | "true" | "while"
+        "if" | "in" | "let" | "loop" | "return" | "true" | "when" | "while"
         => Err(make_generic_nom_err_new(input)),
         _ => Ok((rest, id)),
     }
@@ -1,14 +1,24 @@
+use evdev_rs::enums::EV_REL;
+
 use super::*;
 
 pub(super) fn key_flags(input: &str) -> ResNew<&str, KeyModifierFlags> {
-    many0(one_of("^!+#"))(input).and_then(|(next, val)| {
+    many0(alt((
+        // '<' and '>' restrict the following modifier to the left or right side
+        map(tuple((one_of("<>"), one_of("^!+#"))), |(side, v)| (v, match side {
+            '<' => ModifierSide::Left,
+            _ => ModifierSide::Right,
+        })),
+        map(one_of("^!+#*"), |v| (v, ModifierSide::Any)),
+    )))(input).and_then(|(next, val)| {
         let mut flags = KeyModifierFlags::new();
-        for v in val {
+        for (v, side) in val {
             match v {
-                '!' => { if !flags.alt { flags.alt(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '^' => { if !flags.ctrl { flags.ctrl(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '+' => { if !flags.shift { flags.shift(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '#' => { if !flags.meta { flags.meta(); } else { return Err(make_generic_nom_err_new(input)); } }
+                '!' => { if !flags.alt { flags.alt(); flags.alt_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '^' => { if !flags.ctrl { flags.ctrl(); flags.ctrl_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '+' => { if !flags.shift { flags.shift(); flags.shift_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '#' => { if !flags.meta { flags.meta(); flags.meta_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '*' => { if !flags.wildcard { flags.wildcard(); } else { return Err(make_generic_nom_err_new(input)); } }
                 _ => unreachable!()
             }
         };
@@ -49,6 +59,44 @@ pub(super) fn key(input: &str) -> ResNew<&str, (Key, KeyModifierFlags)> {
         })
 }
 
+/// A relative axis, i.e. 'wheel', 'hwheel' or the evdev name such as 'rel_x'.
+fn rel_axis(input: &str) -> ResNew<&str, Key> {
+    ident(input).and_then(|(next, (name, _))| {
+        let name = match &*name.to_lowercase() {
+            "wheel" => "REL_WHEEL".to_string(),
+            "hwheel" => "REL_HWHEEL".to_string(),
+            _ => name.to_uppercase(),
+        };
+        if !name.starts_with("REL_") { return Err(make_generic_nom_err_new(input)); }
+
+        let key = Key::from_str(&EventType::EV_REL, &name).map_err(|_| make_generic_nom_err_new(input))?;
+        Ok((next, (key, None)))
+    })
+}
+
+/// A relative axis with an optional direction, i.e. 'wheel up'. Up and right are positive, except for the
+/// vertical pointer axis where down is positive.
+pub(super) fn rel_action(input: &str) -> ResNew<&str, (Key, i32)> {
+    tuple((
+        rel_axis,
+        opt(tuple((ws1, alt((tag("up"), tag("down"), tag("left"), tag("right")))))),
+    ))(input).map(|(next, ((axis, _), direction))| {
+        let value = match direction {
+            None => REL_DIRECTION_ANY,
+            Some((_, direction)) => {
+                let positive = match direction {
+                    "up" => axis.event_code != EventCode::EV_REL(EV_REL::REL_Y),
+                    "down" => axis.event_code == EventCode::EV_REL(EV_REL::REL_Y),
+                    "right" => true,
+                    _ => false,
+                };
+                if positive { REL_DIRECTION_POSITIVE } else { REL_DIRECTION_NEGATIVE }
+            }
+        };
+        (next, ((axis, value), None))
+    })
+}
+
 fn key_state(input: &str) -> ResNew<&str, i32> {
     alt((
         tag("down"), tag("up"),
@@ -93,6 +141,17 @@ mod tests {
             KeyModifierFlags::new())));
     }
 
+    #[test]
+    fn test_rel_action() {
+        let rel = |name| Key::from_str(&EventType::EV_REL, name).unwrap();
+
+        assert_eq!(rel_action("wheel up"), nom_ok((rel("REL_WHEEL"), REL_DIRECTION_POSITIVE)));
+        assert_eq!(rel_action("hwheel left"), nom_ok((rel("REL_HWHEEL"), REL_DIRECTION_NEGATIVE)));
+        assert_eq!(rel_action("rel_y up"), nom_ok((rel("REL_Y"), REL_DIRECTION_NEGATIVE)));
+        assert_eq!(rel_action("rel_wheel_hi_res"), nom_ok((rel("REL_WHEEL_HI_RES"), REL_DIRECTION_ANY)));
+        assert!(rel_action("a").is_err());
+    }
+
     #[test]
     fn test_key_flags() {
         assert_eq!(key_flags("!"), nom_ok(KeyModifierFlags::new().tap_mut(|v| v.alt())));
@@ -110,5 +169,25 @@ mod tests {
                 v.meta();
             })));
         assert_eq!(key_flags("#a!"), nom_ok_rest("a!", KeyModifierFlags::new().tap_mut(|v| v.meta())));
+        assert_eq!(key_flags("*!"), nom_ok(KeyModifierFlags::new().tap_mut(|v| {
+            v.wildcard();
+            v.alt();
+        })));
+        assert!(matches!(key_flags("**"), Err(..)));
+    }
+
+    #[test]
+    fn test_key_flags_side() {
+        assert_eq!(key_flags("<^"), nom_ok(KeyModifierFlags::new().tap_mut(|v| {
+            v.ctrl();
+            v.ctrl_side = ModifierSide::Left;
+        })));
+        assert_eq!(key_flags(">!+"), nom_ok(KeyModifierFlags::new().tap_mut(|v| {
+            v.alt();
+            v.alt_side = ModifierSide::Right;
+            v.shift();
+        })));
+        assert_eq!(key_flags("<a"), nom_ok_rest("<a", KeyModifierFlags::new()));
+        assert!(matches!(key_flags("<^>^"), Err(..)));
     }
 }
@@ -17,28 +17,28 @@ impl ParsedKeyActionVecExt for Vec<ParsedKeyAction> {
         self.into_iter()
             .fold(vec![], |mut acc, v| match v {
                 ParsedKeyAction::KeyAction(action) => {
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_DOWN)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_DOWN)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_DOWN)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_DOWN)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_DOWN)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_DOWN)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_DOWN)); }
                     acc.push(KeyAction::new(action.key, action.value));
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_UP)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_UP)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_UP)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_UP)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_UP)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_UP)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_UP)); }
                     acc
                 }
                 ParsedKeyAction::KeyClickAction(action) => {
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_DOWN)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_DOWN)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_DOWN)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_DOWN)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_DOWN)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_DOWN)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_DOWN)); }
                     acc.push(KeyAction::new(action.key, TYPE_DOWN));
                     acc.push(KeyAction::new(action.key, TYPE_UP));
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_UP)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_UP)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_UP)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_UP)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_UP)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_UP)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_UP)); }
                     acc
                 }
             })
@@ -47,6 +47,10 @@ impl ParsedKeyActionVecExt for Vec<ParsedKeyAction> {
 
 pub(super) fn key_action(input: &str) -> ResNew<&str, ParsedKeyAction> {
     alt((
+        // relative events, i.e. '{wheel up}'
+        map(tuple((tag_custom("{"), rel_action, tag_custom("}"))), |(_, ((axis, value), _), _)| {
+            ((axis, KeyModifierFlags::new()), Some(value))
+        }),
         map(tuple((tag_custom("{"), key_with_state, tag_custom("}"))), |(_, (v, _), _)| (v.0, Some(v.1))),
         map(
             alt((
@@ -69,20 +73,23 @@ pub(super) fn key_action(input: &str) -> ResNew<&str, ParsedKeyAction> {
     })
 }
 
-pub(super) fn key_action_with_flags(input: &str) -> ResNew<&str, ParsedKeyAction> {
-    tuple((
-        key_flags,
-        key_action,
-    ))(input).and_then(|(next, parts)| {
-        let flags = parts.0;
-        let mut action = parts.1;
-
-        match &mut action.0 {
-            ParsedKeyAction::KeyAction(action) => { action.modifiers.apply_from(&flags.0) }
-            ParsedKeyAction::KeyClickAction(action) => { action.modifiers.apply_from(&flags.0) }
+impl ParsedKeyAction {
+    pub(super) fn with_flags(mut self, flags: &KeyModifierFlags) -> Self {
+        match &mut self {
+            ParsedKeyAction::KeyAction(action) => { action.modifiers.apply_from(flags) }
+            ParsedKeyAction::KeyClickAction(action) => { action.modifiers.apply_from(flags) }
         }
+        self
+    }
+}
 
-        Ok((next, (action.0, None)))
+pub(super) fn key_action_with_flags(input: &str) -> ResNew<&str, ParsedKeyAction> {
+    alt((
+        tuple((key_flags, key_action)),
+        // flag characters are also valid keys (i.e. 'a::*')
+        map(key_action, |action| ((KeyModifierFlags::new(), None), action)),
+    ))(input).map(|(next, (flags, action))| {
+        (next, (action.0.with_flags(&flags.0), None))
     })
 }
 
@@ -102,6 +109,17 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn test_rel_action() {
+        assert_eq!(key_action("{wheel down}"), nom_ok(ParsedKeyAction::KeyAction(
+            KeyActionWithMods::new(Key::from_str(&EventType::EV_REL, "REL_WHEEL").unwrap(), REL_DIRECTION_NEGATIVE, KeyModifierFlags::new())
+        )));
+
+        assert_eq!(key_action("{hwheel}"), nom_ok(ParsedKeyAction::KeyAction(
+            KeyActionWithMods::new(Key::from_str(&EventType::EV_REL, "REL_HWHEEL").unwrap(), REL_DIRECTION_ANY, KeyModifierFlags::new())
+        )));
+    }
+
     #[test]
     fn test_flags() {
         assert_eq!(key_action_with_flags("+{a down}"), nom_ok(ParsedKeyAction::KeyAction(
@@ -111,5 +129,16 @@ mod tests {
         assert_eq!(key_action_with_flags("!{j down}"), nom_ok(ParsedKeyAction::KeyAction(
             KeyActionWithMods::new(Key::from_str(&EventType::EV_KEY, "KEY_J").unwrap(), 1, KeyModifierFlags::new().tap_mut(|v| v.alt()))
         )));
+
+        assert_eq!(key_action_with_flags("*a"), nom_ok(ParsedKeyAction::KeyClickAction(
+            KeyClickActionWithMods::new_with_mods(*KEY_A, KeyModifierFlags::new().tap_mut(|v| v.wildcard()))
+        )));
+
+        assert_eq!(key_action_with_flags("*"), nom_ok(ParsedKeyAction::KeyClickAction(
+            KeyClickActionWithMods::new_with_mods(
+                Key::from_str(&EventType::EV_KEY, "KEY_8").unwrap(),
+                KeyModifierFlags::new().tap_mut(|v| v.shift()),
+            )
+        )));
     }
 }
\ No newline at end of file
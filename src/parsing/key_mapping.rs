@@ -1,8 +1,39 @@
+use nom::combinator::{eof, peek};
+
 use super::*;
 
+fn trigger_end(input: &str) -> ResNew<&str, ()> {
+    peek(alt((tag_custom("::"), eof)))(input)
+        .map(|(next, _)| (next, ((), None)))
+}
+
+/// A key that is held while the trigger is pressed, i.e. 'capslock' in 'capslock & h'.
+fn custom_modifier(input: &str) -> ResNew<&str, Key> {
+    tuple((key, ws0, tag_custom("&"), ws0))(input)
+        .map(|(next, ((key, _), _, _, _))| (next, (key.0, None)))
+}
+
+/// A mapping trigger, the '~' prefix keeps the held modifiers pressed while the mapping is executed.
+pub(super) fn trigger(input: &str) -> ResNew<&str, (ParsedKeyAction, bool)> {
+    let with_custom = |mut flags: KeyModifierFlags, custom: Option<(Key, Option<CustomError<&str>>)>| {
+        flags.custom = custom.map(|(key, _)| key);
+        flags
+    };
+
+    // flag characters are valid keys as well, only treat them as flags if a complete trigger follows
+    alt((
+        map(tuple((tag_custom("~"), opt(custom_modifier), key_flags, key_action, trigger_end)),
+            move |(_, custom, (flags, _), (action, _), _)| ((action.with_flags(&with_custom(flags, custom)), true), None)),
+        map(tuple((opt(custom_modifier), key_flags, key_action, trigger_end)),
+            move |(custom, (flags, _), (action, _), _)| ((action.with_flags(&with_custom(flags, custom)), false), None)),
+        map(tuple((opt(custom_modifier), key_action, trigger_end)),
+            move |(custom, (action, _), _)| ((action.with_flags(&with_custom(KeyModifierFlags::new(), custom)), false), None)),
+    ))(input)
+}
+
 pub(super) fn key_mapping_inline(input: &str) -> ResNew<&str, Expr> {
     tuple((
-        key_action_with_flags,
+        trigger,
         tag_custom("::"),
         alt((
             key_sequence,
@@ -10,72 +41,83 @@ pub(super) fn key_mapping_inline(input: &str) -> ResNew<&str, Expr> {
         ))
     )
     )(input).and_then(|(next, v)| {
-        let (from, mut to) = (v.0.0, v.2.0);
+        let ((from, passthrough), mut to) = (v.0.0, v.2.0);
 
         let expr = match from {
             ParsedKeyAction::KeyAction(from) => {
-                if to.len() == 1 {
-                    let to = to.remove(0);
+                match to.len() {
                     // action to click
-                    if let ParsedKeyAction::KeyClickAction(to) = to {
-                        return Ok((next, (Expr::map_key_action_click(from, to), None)));
+                    1 if matches!(to[0], ParsedKeyAction::KeyClickAction(_)) => {
+                        match to.remove(0) {
+                            ParsedKeyAction::KeyClickAction(to) => Expr::map_key_action_click(from, to),
+                            _ => unreachable!(),
+                        }
                     }
                     // action to action
-                    if let ParsedKeyAction::KeyAction(to) = to {
-                        return Ok((next, (Expr::map_key_action_action(from, to), None)));
+                    1 => {
+                        match to.remove(0) {
+                            ParsedKeyAction::KeyAction(to) => Expr::map_key_action_action(from, to),
+                            _ => unreachable!(),
+                        }
                     }
+                    // action to seq
+                    _ => Expr::map_key_block(from, Block::new()
+                        .tap_mut(|b| b.statements = to
+                            .to_key_actions()
+                            .into_iter()
+                            .map(|v| Stmt::Expr(Expr::KeyAction(v)))
+                            .collect()),
+                    ),
                 }
-
-                // action to seq
-                Expr::map_key_block(from, Block::new()
-                    .tap_mut(|b| b.statements = to
-                        .to_key_actions()
-                        .into_iter()
-                        .map(|v| Stmt::Expr(Expr::KeyAction(v)))
-                        .collect()),
-                )
             }
             ParsedKeyAction::KeyClickAction(from) => {
-                if to.len() == 1 {
+                match to.len() {
                     // click to click
-                    if let Some(ParsedKeyAction::KeyClickAction(to)) = to.get(0) {
-                        return Ok((next, (Expr::map_key_click(&from, to), None)));
+                    1 if matches!(to[0], ParsedKeyAction::KeyClickAction(_)) => {
+                        match to.remove(0) {
+                            ParsedKeyAction::KeyClickAction(to) => Expr::map_key_click(&from, &to),
+                            _ => unreachable!(),
+                        }
                     }
                     // click to action
-                    if let ParsedKeyAction::KeyAction(to) = to.remove(0) {
-                        return Ok((next, (Expr::map_key_click_action(from, to), None)));
+                    1 => {
+                        match to.remove(0) {
+                            ParsedKeyAction::KeyAction(to) => Expr::map_key_click_action(from, to),
+                            _ => unreachable!(),
+                        }
                     }
+                    // click to seq
+                    _ => Expr::map_key_click_block(from, Block::new()
+                        .tap_mut(|b| b.statements = to
+                            .to_key_actions()
+                            .into_iter()
+                            .map(|v| Stmt::Expr(Expr::KeyAction(v)))
+                            .collect()),
+                    ),
                 }
-
-                // click to seq
-                Expr::map_key_click_block(from, Block::new()
-                    .tap_mut(|b| b.statements = to
-                        .to_key_actions()
-                        .into_iter()
-                        .map(|v| Stmt::Expr(Expr::KeyAction(v)))
-                        .collect()),
-                )
             }
         };
 
+        let expr = if passthrough { expr.passthrough_modifiers() } else { expr };
         Ok((next, (expr, None)))
     })
 }
 
 pub(super) fn key_mapping(input: &str) -> ResNew<&str, Expr> {
     tuple((
-        key_action_with_flags,
+        trigger,
         tag_custom("::"),
         ws0,
         block,
     ))(input).and_then(|(next, v)| {
-        let ((from, _), (to, last_err)) = (v.0, v.3);
+        let (((from, passthrough), _), (to, last_err)) = (v.0, v.3);
 
         let expr = match from {
             ParsedKeyAction::KeyClickAction(from) => { Expr::map_key_click_block(from, to) }
             ParsedKeyAction::KeyAction(from) => { Expr::map_key_block(from, to) }
         };
 
+        let expr = if passthrough { expr.passthrough_modifiers() } else { expr };
         Ok((next, (expr, last_err)))
     })
 }
@@ -126,6 +168,64 @@ mod tests {
         ])));
     }
 
+    #[test]
+    fn test_key_mapping_passthrough() {
+        let from = KeyClickActionWithMods::new(*KEY_A).tap_mut(|v| { v.modifiers.wildcard(); });
+        let to = KeyClickActionWithMods::new(*KEY_B);
+
+        assert_eq!(key_mapping_inline("*a::b"), nom_ok(Expr::map_key_click(&from, &to)));
+        assert_eq!(key_mapping_inline("~*a::b"), nom_ok(Expr::KeyMapping(vec![
+            KeyMapping {
+                from: KeyActionWithMods::new(*KEY_A, TYPE_DOWN, from.modifiers),
+                to: Block::new().tap_mut(|b| { b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_DOWN))); }),
+            },
+            KeyMapping {
+                from: KeyActionWithMods::new(*KEY_A, TYPE_UP, from.modifiers),
+                to: Block::new().tap_mut(|b| { b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_UP))); }),
+            },
+            KeyMapping {
+                from: KeyActionWithMods::new(*KEY_A, TYPE_REPEAT, from.modifiers),
+                to: Block::new().tap_mut(|b| { b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_REPEAT))); }),
+            },
+        ])));
+
+        // flag characters are also valid keys
+        assert_eq!(key_mapping_inline("*::a"), nom_ok(Expr::map_key_click(
+            &KeyClickActionWithMods::new(Key::from_str(&EventType::EV_KEY, "KEY_8").unwrap()).tap_mut(|v| { v.modifiers.shift(); }),
+            &KeyClickActionWithMods::new(*KEY_A),
+        )));
+        assert_eq!(key_mapping_inline("~::a"), nom_ok(Expr::map_key_click(
+            &KeyClickActionWithMods::new(Key::from_str(&EventType::EV_KEY, "KEY_GRAVE").unwrap()),
+            &KeyClickActionWithMods::new(*KEY_A),
+        )));
+    }
+
+    #[test]
+    fn test_key_mapping_side_specific() {
+        let from = KeyClickActionWithMods::new(*KEY_A).tap_mut(|v| {
+            v.modifiers.ctrl();
+            v.modifiers.ctrl_side = ModifierSide::Left;
+        });
+        let to = KeyClickActionWithMods::new(*KEY_B).tap_mut(|v| {
+            v.modifiers.alt();
+            v.modifiers.alt_side = ModifierSide::Right;
+        });
+
+        assert_eq!(key_mapping_inline("<^a::>!b"), nom_ok(Expr::map_key_click(&from, &to)));
+    }
+
+    #[test]
+    fn test_key_mapping_custom_modifier() {
+        let from = KeyClickActionWithMods::new(*KEY_H).tap_mut(|v| { v.modifiers.custom = Some(*KEY_CAPSLOCK); });
+        let to = KeyClickActionWithMods::new(*KEY_LEFT);
+
+        assert_eq!(key_mapping_inline("capslock & h::left"), nom_ok(Expr::map_key_click(&from, &to)));
+        assert_eq!(key_mapping_inline("capslock&h::left"), nom_ok(Expr::map_key_click(&from, &to)));
+
+        let from = from.tap_mut(|v| { v.modifiers.shift(); });
+        assert_eq!(key_mapping_inline("capslock & +h::left"), nom_ok(Expr::map_key_click(&from, &to)));
+    }
+
     #[test]
     fn test_key_mapping_complex() {
         // TODO add when implemented
@@ -29,6 +29,7 @@ use return_statement::*;
 #[cfg(test)]
 use tests::*;
 use variable::*;
+use when_statement::*;
 
 use crate::*;
 
@@ -49,6 +50,7 @@ mod primitives;
 mod variable;
 mod for_loop;
 mod error;
+mod when_statement;
 
 
 fn stmt(input: &str) -> ResNew<&str, Stmt> {
@@ -57,6 +59,7 @@ fn stmt(input: &str) -> ResNew<&str, Stmt> {
         continue_statement,
         if_stmt,
         for_loop,
+        when_stmt,
         map(
             tuple((expr, tag_custom(";"))),
             |(v, _)| (Stmt::Expr(v.0), v.1),
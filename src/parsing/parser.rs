@@ -44,16 +44,24 @@ pub(crate) fn parse_key_sequence(raw: &str) -> Result<Vec<KeyAction>> {
     }
 }
 
+pub(crate) fn parse_key(raw: &str) -> Result<Key> {
+    match key(raw) {
+        Ok(("", ((key, _), _))) => Ok(key),
+        _ => Err(anyhow!("failed to parse key '{}'", raw)),
+    }
+}
+
 pub(crate) fn parse_key_action_with_mods(from: &str, to: Block) -> Result<Expr> {
-    let from = key_action_with_flags(from).expect("failed to parse mapping trigger");
+    let from = trigger(from).expect("failed to parse mapping trigger");
     if !from.0.is_empty() { return Err(anyhow!("failed to parse mapping trigger")); }
-    let from = from.1;
+    let (from, passthrough) = from.1.0;
 
-    let expr = match from.0 {
+    let expr = match from {
         ParsedKeyAction::KeyClickAction(from) => { Expr::map_key_click_block(from, to) }
         ParsedKeyAction::KeyAction(from) => { Expr::map_key_block(from, to) }
     };
 
+    let expr = if passthrough { expr.passthrough_modifiers() } else { expr };
     Ok(expr)
 }
 
@@ -0,0 +1,81 @@
+use super::*;
+
+fn condition(input: &str) -> ResNew<&str, (ConditionProperty, Expr)> {
+    tuple((
+        alt((
+            tag_custom("window_class"),
+            tag_custom("window_instance"),
+            tag_custom("window_title"),
+            tag_custom("device_name"),
+            tag_custom("device_path"),
+            tag_custom("device_id"),
+        )),
+        ws0,
+        tag_custom("("),
+        ws0,
+        expr,
+        ws0,
+        tag_custom(")"),
+    ))(input).map(|(next, v)| {
+        let property = match v.0 {
+            "window_class" => ConditionProperty::WindowClass,
+            "window_instance" => ConditionProperty::WindowInstance,
+            "window_title" => ConditionProperty::WindowTitle,
+            "device_name" => ConditionProperty::DeviceName,
+            "device_path" => ConditionProperty::DevicePath,
+            "device_id" => ConditionProperty::DeviceId,
+            _ => unreachable!(),
+        };
+        (next, ((property, v.4.0), None))
+    })
+}
+
+pub(super) fn when_stmt(input: &str) -> ResNew<&str, Stmt> {
+    let (input, _) = tag_custom("when")(input)?;
+
+    tuple((
+        ws1,
+        condition,
+        many0(tuple((
+            ws0,
+            tag_custom("&&"),
+            ws0,
+            condition,
+        ))),
+        ws0,
+        block,
+    ))(input).map(|(next, v)| {
+        let mut conditions: Vec<(ConditionProperty, Expr)> = v.2.into_iter().map(|v| v.3.0).collect();
+        conditions.insert(0, v.1.0);
+
+        (next, (Stmt::When(conditions, v.4.0), v.4.1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_when() {
+        assert_eq!(when_stmt("when window_class(\"firefox\") { a::b; }"), nom_ok(Stmt::When(vec![
+            (ConditionProperty::WindowClass, Expr::Value(ValueType::String("firefox".to_string()))),
+        ], nom_eval(block("{a::b;}")),
+        )));
+
+        assert_eq!(nom_no_last_err(stmt("when window_class(\"firefox\") && window_title(\".*YouTube.*\"){ a::b; }")),
+                   nom_ok(Stmt::When(vec![
+                       (ConditionProperty::WindowClass, Expr::Value(ValueType::String("firefox".to_string()))),
+                       (ConditionProperty::WindowTitle, Expr::Value(ValueType::String(".*YouTube.*".to_string()))),
+                   ], nom_eval(block("{a::b;}")),
+                   )));
+
+        assert_eq!(nom_no_last_err(stmt("when device_id(\"046d:c52b\"){ a::b; }")),
+                   nom_ok(Stmt::When(vec![
+                       (ConditionProperty::DeviceId, Expr::Value(ValueType::String("046d:c52b".to_string()))),
+                   ], nom_eval(block("{a::b;}")),
+                   )));
+
+        assert!(matches!(when_stmt("when window_size(\"firefox\") { a::b; }"), Err(..)));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::*;
+
+// frames further apart are treated as the start of a new movement
+const MAX_FRAME_INTERVAL_MS: f64 = 100.0;
+
+/// A single setting of a pointer profile.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PointerSetting {
+    /// the factors for the horizontal and vertical movement
+    Scale(f64, f64),
+    /// the acceleration factors at given speeds (in units per millisecond), sorted by speed
+    Acceleration(Vec<(f64, f64)>),
+    /// the axes whose direction is inverted, any of 'x', 'y', 'wheel' and 'hwheel'
+    Invert(Vec<String>),
+    /// the factors for the vertical and horizontal wheel
+    WheelMultiplier(f64, f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PointerProfile {
+    scale: (f64, f64),
+    acceleration: Vec<(f64, f64)>,
+    invert: Vec<String>,
+    wheel_multiplier: (f64, f64),
+}
+
+impl PointerProfile {
+    fn new() -> Self {
+        PointerProfile { scale: (1.0, 1.0), acceleration: vec![], invert: vec![], wheel_multiplier: (1.0, 1.0) }
+    }
+
+    fn apply(&mut self, setting: PointerSetting) {
+        match setting {
+            PointerSetting::Scale(x, y) => self.scale = (x, y),
+            PointerSetting::Acceleration(points) => self.acceleration = points,
+            PointerSetting::Invert(axes) => self.invert = axes,
+            PointerSetting::WheelMultiplier(vertical, horizontal) => self.wheel_multiplier = (vertical, horizontal),
+        }
+    }
+
+    fn direction(&self, axis: &str) -> f64 {
+        if self.invert.iter().any(|v| v == axis) { -1.0 } else { 1.0 }
+    }
+}
+
+// the state of a single input device
+#[derive(Default)]
+struct DeviceMotion {
+    // the movement of the current frame, which is sent once the frame is complete
+    pending: Option<(i32, i32)>,
+    // the kernel timestamp of the last frame in milliseconds
+    last_frame: Option<f64>,
+    // fractions carried over to the next event of the same axis
+    remainders: HashMap<Key, f64>,
+}
+
+impl DeviceMotion {
+    fn take_whole(&mut self, axis: Key, value: f64) -> i32 {
+        let remainder = self.remainders.entry(axis).or_insert(0.0);
+        *remainder += value;
+        let whole = remainder.trunc();
+        *remainder -= whole;
+        whole as i32
+    }
+}
+
+/// Scales, accelerates and inverts relative events of pointing devices.
+///
+/// Profiles are scoped by conditions just like mappings, so each device can have its own profile, the most
+/// specific profile is used. Pointer movement is held back until its frame is complete, so both axes can be
+/// accelerated by the speed of the whole movement. Devices without a profile are passed through untouched.
+pub struct PointerTransform {
+    profiles: Vec<(KeyActionCondition, PointerProfile)>,
+    devices: HashMap<String, DeviceMotion>,
+}
+
+impl PointerTransform {
+    pub fn new() -> Self {
+        PointerTransform { profiles: vec![], devices: Default::default() }
+    }
+
+    pub fn set(&mut self, condition: KeyActionCondition, setting: PointerSetting) {
+        match self.profiles.iter_mut().find(|(v, _)| *v == condition) {
+            Some((_, profile)) => profile.apply(setting),
+            None => {
+                let mut profile = PointerProfile::new();
+                profile.apply(setting);
+                self.profiles.push((condition, profile));
+            }
+        }
+    }
+
+    fn profile_index(&self, window: Option<&ActiveWindowInfo>, device: &DeviceInfo) -> Option<usize> {
+        self.profiles.iter()
+            .enumerate()
+            .filter(|(_, (condition, _))| condition.is_satisfied(window, Some(device)))
+            .max_by_key(|(_, (condition, _))| condition.specificity())
+            .map(|(index, _)| index)
+    }
+
+    /// Gets the events an input event turned into.
+    pub fn handle_event(&mut self, ev: &InputEvent, device: &DeviceInfo, window: Option<&ActiveWindowInfo>)
+                        -> Vec<InputEvent> {
+        match ev.event_code {
+            EventCode::EV_REL(_) | EventCode::EV_SYN(_) => {}
+            _ => return vec![ev.clone()],
+        }
+
+        let profile = match self.profile_index(window, device) {
+            Some(index) => &self.profiles[index].1,
+            None => return vec![ev.clone()],
+        };
+        let motion = self.devices.entry(device.path.clone()).or_default();
+        let key = Key { event_code: ev.event_code };
+
+        if ev.event_code == SYN_REPORT.event_code {
+            let mut events = vec![];
+            if let Some((x, y)) = motion.pending.take() {
+                // the events may have been queued for a while, the time they were read by the kernel is used so
+                // bursts don't look faster than they were
+                let now = ev.time.tv_sec as f64 * 1000.0 + ev.time.tv_usec as f64 / 1000.0;
+                let interval = motion.last_frame
+                    .map_or(MAX_FRAME_INTERVAL_MS, |last_frame| now - last_frame)
+                    .max(1.0).min(MAX_FRAME_INTERVAL_MS);
+                motion.last_frame = Some(now);
+
+                let speed = ((x * x + y * y) as f64).sqrt() / interval;
+                let factor = acceleration_factor(&profile.acceleration, speed);
+
+                let x = motion.take_whole(*REL_X, x as f64 * profile.scale.0 * factor * profile.direction("x"));
+                let y = motion.take_whole(*REL_Y, y as f64 * profile.scale.1 * factor * profile.direction("y"));
+                if x != 0 { events.push(KeyAction::new(*REL_X, x).to_input_ev()); }
+                if y != 0 { events.push(KeyAction::new(*REL_Y, y).to_input_ev()); }
+            }
+            events.push(ev.clone());
+            return events;
+        }
+
+        let value = ev.value as f64;
+        let value = if key == *REL_X || key == *REL_Y {
+            let pending = motion.pending.get_or_insert((0, 0));
+            if key == *REL_X { pending.0 += ev.value; } else { pending.1 += ev.value; }
+            return vec![];
+        } else if key == *REL_WHEEL || key == *REL_WHEEL_HI_RES {
+            value * profile.wheel_multiplier.0 * profile.direction("wheel")
+        } else if key == *REL_HWHEEL || key == *REL_HWHEEL_HI_RES {
+            value * profile.wheel_multiplier.1 * profile.direction("hwheel")
+        } else {
+            return vec![ev.clone()];
+        };
+
+        match motion.take_whole(key, value) {
+            0 => vec![],
+            value => vec![KeyAction::new(key, value).to_input_ev()],
+        }
+    }
+}
+
+/// Interpolates the factor at the given speed between the points, the factor stays constant outside of them.
+fn acceleration_factor(points: &[(f64, f64)], speed: f64) -> f64 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 1.0,
+    };
+    if speed <= first.0 { return first.1; }
+    if speed >= last.0 { return last.1; }
+
+    points.windows(2)
+        .find(|v| speed <= v[1].0)
+        .map(|v| {
+            let ((from_speed, from), (to_speed, to)) = (v[0], v[1]);
+            from + (to - from) * (speed - from_speed) / (to_speed - from_speed)
+        })
+        .unwrap_or(last.1)
+}
+
+/// Parses acceleration points written as 'speed:factor' pairs that are separated by spaces.
+pub fn parse_acceleration_points(raw: &str) -> Result<Vec<(f64, f64)>> {
+    let mut points = raw.split_whitespace()
+        .map(|point| {
+            let mut parts = point.splitn(2, ':');
+            match (parts.next().map(str::parse::<f64>), parts.next().map(str::parse::<f64>)) {
+                (Some(Ok(speed)), Some(Ok(factor))) if speed >= 0.0 => Ok((speed, factor)),
+                _ => Err(anyhow!("invalid acceleration point '{}'", point)),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    Ok(points)
+}
+
+/// Parses the axes to invert, separated by spaces.
+pub fn parse_inverted_axes(raw: &str) -> Result<Vec<String>> {
+    raw.split_whitespace()
+        .map(|axis| match axis {
+            "x" | "y" | "wheel" | "hwheel" => Ok(axis.to_string()),
+            _ => Err(anyhow!("invalid axis '{}'", axis)),
+        })
+        .collect()
+}
+
+/// Parses a pointer profile file, each line holds a setting such as 'scale = 1.5' or 'invert = y wheel'. Empty
+/// lines and lines starting with '#' are ignored.
+pub fn parse_pointer_config(raw: &str) -> Result<Vec<PointerSetting>> {
+    let numbers = |value: &str| value.split_whitespace().map(str::parse::<f64>).collect::<Result<Vec<_>, _>>();
+
+    let mut settings = vec![];
+    for line in raw.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut parts = line.splitn(2, '=').map(str::trim);
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name, value),
+            _ => return Err(anyhow!("invalid pointer setting '{}'", line)),
+        };
+
+        let setting = match (name, numbers(value).as_deref()) {
+            ("scale", Ok([factor])) => PointerSetting::Scale(*factor, *factor),
+            ("scale", Ok([x, y])) => PointerSetting::Scale(*x, *y),
+            ("wheel_multiplier", Ok([factor])) => PointerSetting::WheelMultiplier(*factor, *factor),
+            ("wheel_multiplier", Ok([vertical, horizontal])) => PointerSetting::WheelMultiplier(*vertical, *horizontal),
+            ("acceleration", _) => PointerSetting::Acceleration(parse_acceleration_points(value)?),
+            ("invert", _) => PointerSetting::Invert(parse_inverted_axes(value)?),
+            _ => return Err(anyhow!("invalid pointer setting '{}'", line)),
+        };
+        settings.push(setting);
+    }
+    Ok(settings)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use evdev_rs::TimeVal;
+
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    #[test]
+    fn test_acceleration_factor() {
+        let points = parse_acceleration_points("4:3 0:1 2:1").unwrap();
+        assert_eq!(acceleration_factor(&points, 0.0), 1.0);
+        assert_eq!(acceleration_factor(&points, 2.0), 1.0);
+        assert_eq!(acceleration_factor(&points, 3.0), 2.0);
+        assert_eq!(acceleration_factor(&points, 10.0), 3.0);
+        assert_eq!(acceleration_factor(&[], 10.0), 1.0);
+        assert!(parse_acceleration_points("1:a").is_err());
+    }
+
+    #[test]
+    fn test_transform() {
+        let device = device("/dev/input/event3", "Test Mouse");
+        let mut transform = PointerTransform::new();
+        assert_eq!(transform.handle_event(&ev(*REL_X, 3), &device, None), vec![ev(*REL_X, 3)]);
+
+        transform.set(KeyActionCondition::new(), PointerSetting::Scale(1.5, 2.0));
+        transform.set(KeyActionCondition::new(), PointerSetting::Invert(vec!["y".to_string(), "wheel".to_string()]));
+        transform.set(KeyActionCondition::new(), PointerSetting::WheelMultiplier(0.5, 1.0));
+
+        // the movement is sent once the frame is complete
+        assert_eq!(transform.handle_event(&ev(*REL_X, 3), &device, None), vec![]);
+        assert_eq!(transform.handle_event(&ev(*REL_Y, 2), &device, None), vec![]);
+        assert_eq!(transform.handle_event(&SYN_REPORT.clone(), &device, None), vec![
+            ev(*REL_X, 4),
+            ev(*REL_Y, -4),
+            SYN_REPORT.clone(),
+        ]);
+
+        // fractions are carried over to the next frame
+        transform.handle_event(&ev(*REL_X, 1), &device, None);
+        assert_eq!(transform.handle_event(&SYN_REPORT.clone(), &device, None), vec![ev(*REL_X, 2), SYN_REPORT.clone()]);
+
+        assert_eq!(transform.handle_event(&ev(*REL_WHEEL, 1), &device, None), vec![]);
+        assert_eq!(transform.handle_event(&ev(*REL_WHEEL, 1), &device, None), vec![ev(*REL_WHEEL, -1)]);
+        assert_eq!(transform.handle_event(&ev(*REL_HWHEEL, 1), &device, None), vec![ev(*REL_HWHEEL, 1)]);
+    }
+
+    #[test]
+    fn test_acceleration_by_frame_time() {
+        let device = device("/dev/input/event3", "Test Mouse");
+        let mut transform = PointerTransform::new();
+        transform.set(KeyActionCondition::new(), PointerSetting::Acceleration(vec![(1.0, 1.0), (2.0, 2.0)]));
+
+        let syn = |ms: i64| InputEvent { time: TimeVal { tv_sec: 10, tv_usec: ms * 1000 }, ..SYN_REPORT.clone() };
+        transform.handle_event(&ev(*REL_X, 10), &device, None);
+        assert_eq!(transform.handle_event(&syn(0), &device, None), vec![ev(*REL_X, 10), syn(0)]);
+
+        // 10 pixels within 5ms are accelerated, no matter when the frame is processed
+        transform.handle_event(&ev(*REL_X, 10), &device, None);
+        assert_eq!(transform.handle_event(&syn(5), &device, None), vec![ev(*REL_X, 20), syn(5)]);
+        transform.handle_event(&ev(*REL_X, 10), &device, None);
+        assert_eq!(transform.handle_event(&syn(15), &device, None), vec![ev(*REL_X, 10), syn(15)]);
+    }
+
+    #[test]
+    fn test_pointer_config() {
+        let settings = parse_pointer_config("# comment\nscale = 2\n\nacceleration = 0:1 5:2\ninvert = wheel\nwheel_multiplier = 3 1\n").unwrap();
+        assert_eq!(settings, vec![
+            PointerSetting::Scale(2.0, 2.0),
+            PointerSetting::Acceleration(vec![(0.0, 1.0), (5.0, 2.0)]),
+            PointerSetting::Invert(vec!["wheel".to_string()]),
+            PointerSetting::WheelMultiplier(3.0, 1.0),
+        ]);
+        assert!(parse_pointer_config("speed = 2").is_err());
+        assert!(parse_pointer_config("invert = z").is_err());
+    }
+}
@@ -3,7 +3,7 @@ use tokio::process::Command;
 
 use crate::*;
 use crate::messaging::ExecutionMessage;
-use crate::parsing::parser::{parse_key_action_with_mods, parse_key_sequence};
+use crate::parsing::parser::{parse_key, parse_key_action_with_mods, parse_key_sequence};
 
 pub async fn throw_error<'a>(err: anyhow::Error, exit_code: i32, amb: &mut Ambient<'a>) -> ValueType {
     amb.message_tx.borrow_mut().as_ref().unwrap()
@@ -44,11 +44,313 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
             let actions = parse_key_sequence(&*val).unwrap();
 
             for action in actions {
-                amb.ev_writer_tx.send(action.to_input_ev()).await.unwrap();
-                amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
+                amb.ev_writer_tx.send(action.to_input_ev().into()).await.unwrap();
+                amb.ev_writer_tx.send(SYN_REPORT.clone().into()).await.unwrap();
             }
         }
 
+        "mouse_move" => {
+            let (dx, dy) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(dx)), Some(ValueType::Number(dy))) => (*dx as i32, *dy as i32),
+                _ => return Err(anyhow!("function 'mouse_move' expects two numbers")),
+            };
+
+            for ev in move_events(dx, dy) { amb.ev_writer_tx.send(ev.into()).await.unwrap(); }
+        }
+        "scroll" => {
+            let (vertical, horizontal) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(vertical)), None) => (*vertical, 0.0),
+                (Some(ValueType::Number(vertical)), Some(ValueType::Number(horizontal))) => (*vertical, *horizontal),
+                _ => return Err(anyhow!("function 'scroll' expects one or two numbers")),
+            };
+
+            // fractions of a notch are carried over between calls
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::Scroll(vertical, horizontal, tx)).await.unwrap();
+            for ev in rx.recv().await.unwrap() { amb.ev_writer_tx.send(ev.into()).await.unwrap(); }
+        }
+        "click" => {
+            let button = match parsed_args.get(0) {
+                None => *BTN_LEFT,
+                Some(ValueType::String(button)) => parse_button(button)?,
+                Some(_) => return Err(anyhow!("function 'click' expects a button name")),
+            };
+
+            for value in [TYPE_DOWN, TYPE_UP].iter() {
+                amb.ev_writer_tx.send(KeyAction::new(button, *value).to_input_ev().into()).await.unwrap();
+                amb.ev_writer_tx.send(SYN_REPORT.clone().into()).await.unwrap();
+            }
+        }
+
+        "mouse_keys_move" | "mouse_keys_scroll" => {
+            let (x, y) = match parsed_args.get(0) {
+                Some(ValueType::String(direction)) => parse_direction(direction)?,
+                _ => return Err(anyhow!("function '{}' expects a direction argument", name)),
+            };
+            let invocation = match &amb.invocation {
+                Some(invocation) => invocation.clone(),
+                None => return Err(anyhow!("function '{}' can only be used in mappings triggered by a key press", name)),
+            };
+
+            let action = match &**name {
+                "mouse_keys_move" => MouseKeysAction::Move(x, y),
+                // scrolling up is positive
+                _ => MouseKeysAction::Scroll(-y, x),
+            };
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::MouseKeysPress(action, invocation)).await.unwrap();
+        }
+        "mouse_keys_drag" => {
+            let button = match parsed_args.get(0) {
+                None => *BTN_LEFT,
+                Some(ValueType::String(button)) => parse_button(button)?,
+                Some(_) => return Err(anyhow!("function 'mouse_keys_drag' expects a button name")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::MouseKeysDrag(button)).await.unwrap();
+        }
+        "mouse_keys_speed" => {
+            let (initial_speed, max_speed) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(initial_speed)), Some(ValueType::Number(max_speed)))
+                if *initial_speed >= 0.0 && *max_speed >= 0.0 => (*initial_speed, *max_speed),
+                _ => return Err(anyhow!("function 'mouse_keys_speed' expects two positive numbers")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetMouseKeysSpeed(initial_speed, max_speed)).await.unwrap();
+        }
+        "mouse_keys_acceleration" => {
+            let duration = match parsed_args.get(0) {
+                Some(ValueType::Number(duration)) if *duration >= 0.0 => *duration,
+                _ => return Err(anyhow!("function 'mouse_keys_acceleration' expects a positive duration")),
+            };
+            let exponent = match parsed_args.get(1) {
+                None => 2.0,
+                Some(ValueType::Number(exponent)) if *exponent > 0.0 => *exponent,
+                Some(_) => return Err(anyhow!("the exponent passed to 'mouse_keys_acceleration' must be a positive number")),
+            };
+
+            let duration = time::Duration::try_from_secs_f64(duration / 1000.0)
+                .map_err(|_| anyhow!("the duration passed to 'mouse_keys_acceleration' is too long"))?;
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetMouseKeysAcceleration(duration, exponent)).await.unwrap();
+        }
+        "mouse_keys_scroll_rate" => {
+            let rate = match parsed_args.get(0) {
+                Some(ValueType::Number(rate)) if *rate > 0.0 => *rate,
+                _ => return Err(anyhow!("function 'mouse_keys_scroll_rate' expects a positive number")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetMouseKeysScrollRate(rate)).await.unwrap();
+        }
+
+        "scroll_emulation" => {
+            let trigger = match parsed_args.get(0) {
+                None => None,
+                Some(ValueType::String(trigger)) => Some(parse_button(trigger).or_else(|_| parse_key(trigger))?),
+                Some(_) => return Err(anyhow!("function 'scroll_emulation' expects a key or button name")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetScrollEmulation(trigger)).await.unwrap();
+        }
+        "scroll_emulation_sensitivity" => {
+            let sensitivity = match parsed_args.get(0) {
+                Some(ValueType::Number(sensitivity)) if *sensitivity > 0.0 => *sensitivity,
+                _ => return Err(anyhow!("function 'scroll_emulation_sensitivity' expects a positive number")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetScrollEmulationSensitivity(sensitivity)).await.unwrap();
+        }
+        "scroll_emulation_axis_lock" | "scroll_emulation_natural" => {
+            let active = match parsed_args.get(0) {
+                None => true,
+                Some(ValueType::Bool(active)) => *active,
+                Some(_) => return Err(anyhow!("function '{}' expects a boolean argument", name)),
+            };
+
+            let msg = match &**name {
+                "scroll_emulation_axis_lock" => ExecutionMessage::SetScrollEmulationAxisLock(active),
+                _ => ExecutionMessage::SetScrollEmulationNatural(active),
+            };
+            amb.message_tx.as_ref().unwrap().send(msg).await.unwrap();
+        }
+
+        "pointer_scale" | "wheel_multiplier" => {
+            let (first, second) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(factor)), None) => (*factor, *factor),
+                (Some(ValueType::Number(first)), Some(ValueType::Number(second))) => (*first, *second),
+                _ => return Err(anyhow!("function '{}' expects one or two numbers", name)),
+            };
+
+            let setting = match &**name {
+                "pointer_scale" => PointerSetting::Scale(first, second),
+                _ => PointerSetting::WheelMultiplier(first, second),
+            };
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::SetPointerTransform(amb.condition.clone(), setting)).await.unwrap();
+        }
+        "pointer_acceleration" | "pointer_invert" => {
+            let value = match parsed_args.get(0) {
+                None => "".to_string(),
+                Some(ValueType::String(value)) => value.clone(),
+                Some(_) => return Err(anyhow!("function '{}' expects a string argument", name)),
+            };
+
+            let setting = match &**name {
+                "pointer_acceleration" => PointerSetting::Acceleration(parse_acceleration_points(&value)?),
+                _ => PointerSetting::Invert(parse_inverted_axes(&value)?),
+            };
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::SetPointerTransform(amb.condition.clone(), setting)).await.unwrap();
+        }
+        "pointer_config" => {
+            let path = match parsed_args.get(0) {
+                Some(ValueType::String(path)) => path.clone(),
+                _ => return Err(anyhow!("function 'pointer_config' expects a file path")),
+            };
+
+            let config = fs::read_to_string(&path).map_err(|err| anyhow!("failed to read '{}': {}", path, err))?;
+            // the settings are scoped to the devices matched by the surrounding 'when' statements
+            for setting in parse_pointer_config(&config)? {
+                amb.message_tx.as_ref().unwrap()
+                    .send(ExecutionMessage::SetPointerTransform(amb.condition.clone(), setting)).await.unwrap();
+            }
+        }
+
+        "gesture" => {
+            let (gesture, (block, inner_var_map)) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(gesture)), Some(ValueType::Lambda(_, block, var_map))) =>
+                    (parse_gesture(gesture)?, (block.clone(), var_map.clone())),
+                _ => return Err(anyhow!("function 'gesture' expects a gesture and a lambda")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::AddGesture(gesture, block, inner_var_map)).await.unwrap();
+        }
+        "gesture_button" => {
+            let trigger = match parsed_args.get(0) {
+                Some(ValueType::String(trigger)) => parse_button(trigger)?,
+                _ => return Err(anyhow!("function 'gesture_button' expects a button name")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetGestureTrigger(trigger)).await.unwrap();
+        }
+        "gesture_threshold" => {
+            let threshold = match parsed_args.get(0) {
+                Some(ValueType::Number(threshold)) if *threshold >= 1.0 => *threshold as i32,
+                _ => return Err(anyhow!("function 'gesture_threshold' expects a positive number")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetGestureThreshold(threshold)).await.unwrap();
+        }
+        "abs_threshold" => {
+            let (axis, percent, key) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Number(percent)), Some(ValueType::String(key)))
+                if percent.abs() <= 100.0 => (parse_abs_axis(axis)?, *percent, parse_key(key)?),
+                _ => return Err(anyhow!("function 'abs_threshold' expects an axis, a percentage between -100 and 100 and a key")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::AddAbsThreshold(axis, percent, key)).await.unwrap();
+        }
+
+        "gamepad_deadzone" => {
+            let (stick, deadzone) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(stick)), Some(ValueType::Number(percent))) if *percent >= 0.0 && *percent < 100.0 =>
+                    (parse_stick(stick)?, *percent / 100.0),
+                _ => return Err(anyhow!("function 'gamepad_deadzone' expects a stick and a percentage below 100")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetGamepadDeadzone(stick, deadzone)).await.unwrap();
+        }
+        "gamepad_curve" => {
+            let (stick, curve) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(stick)), Some(ValueType::Number(curve))) if *curve > 0.0 =>
+                    (parse_stick(stick)?, *curve),
+                _ => return Err(anyhow!("function 'gamepad_curve' expects a stick and a positive exponent")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetGamepadCurve(stick, curve)).await.unwrap();
+        }
+        "gamepad_swap_sticks" => {
+            let swap_sticks = match parsed_args.get(0) {
+                None => true,
+                Some(ValueType::Bool(swap_sticks)) => *swap_sticks,
+                Some(_) => return Err(anyhow!("function 'gamepad_swap_sticks' expects a boolean argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetGamepadSwapSticks(swap_sticks)).await.unwrap();
+        }
+        "gamepad_key_axis" => {
+            let (key, axis, position) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(key)), Some(ValueType::String(axis)), Some(ValueType::Number(percent)))
+                if percent.abs() <= 100.0 => (parse_key(key)?, parse_abs_axis(axis)?, *percent / 100.0),
+                _ => return Err(anyhow!("function 'gamepad_key_axis' expects a key, an axis and a percentage between -100 and 100")),
+            };
+            if !GAMEPAD_AXES.iter().any(|(gamepad_axis, _)| *gamepad_axis == axis) {
+                return Err(anyhow!("the virtual gamepad has no axis '{}'", axis.event_code));
+            }
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::AddGamepadKeyAxis(key, axis, position)).await.unwrap();
+        }
+
+        "joystick_mouse" => {
+            let (stick, mode, speed) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(stick)), Some(ValueType::String(mode)), None) if mode == "off" =>
+                    (parse_stick(stick)?, None, 0.0),
+                (Some(ValueType::String(stick)), Some(ValueType::String(mode)), None) => {
+                    let mode = parse_joystick_mouse_mode(mode)?;
+                    (parse_stick(stick)?, Some(mode), mode.default_speed())
+                }
+                (Some(ValueType::String(stick)), Some(ValueType::String(mode)), Some(ValueType::Number(speed))) if *speed > 0.0 =>
+                    (parse_stick(stick)?, Some(parse_joystick_mouse_mode(mode)?), *speed),
+                _ => return Err(anyhow!("function 'joystick_mouse' expects a stick, a mode and an optional positive speed")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetJoystickMouse(stick, mode, speed)).await.unwrap();
+        }
+        "joystick_mouse_deadzone" => {
+            let (stick, deadzone) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(stick)), Some(ValueType::Number(percent))) if *percent >= 0.0 && *percent < 100.0 =>
+                    (parse_stick(stick)?, *percent / 100.0),
+                _ => return Err(anyhow!("function 'joystick_mouse_deadzone' expects a stick and a percentage below 100")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetJoystickMouseDeadzone(stick, deadzone)).await.unwrap();
+        }
+        "joystick_mouse_acceleration" => {
+            let (stick, acceleration) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(stick)), Some(ValueType::Number(exponent))) if *exponent > 0.0 =>
+                    (parse_stick(stick)?, *exponent),
+                _ => return Err(anyhow!("function 'joystick_mouse_acceleration' expects a stick and a positive exponent")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetJoystickMouseAcceleration(stick, acceleration)).await.unwrap();
+        }
+
+        "lock_state" => {
+            let led = match parsed_args.get(0) {
+                Some(ValueType::String(name)) => parse_led(name)?,
+                _ => return Err(anyhow!("function 'lock_state' expects a lock key name")),
+            };
+
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetLockState(led, tx)).await.unwrap();
+            return Ok(ValueType::Bool(rx.recv().await.unwrap()));
+        }
+        "set_led" => {
+            let (led, value) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(name)), None) => (parse_led(name)?, None),
+                (Some(ValueType::String(name)), Some(ValueType::Bool(value))) => (parse_led(name)?, Some(*value)),
+                _ => return Err(anyhow!("function 'set_led' expects an LED name and an optional boolean")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetLed(led, value)).await.unwrap();
+        }
+        "layer_led" => {
+            let (layer_name, led) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(layer_name)), Some(ValueType::String(name))) => (layer_name.clone(), parse_led(name)?),
+                _ => return Err(anyhow!("function 'layer_led' expects a layer name and an LED name")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::AddLayerLed(layer_name, led)).await.unwrap();
+        }
+
         "active_window_class" => {
             let (tx, mut rx) = mpsc::channel(1);
             amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetFocusedWindowInfo(tx)).await.unwrap();
@@ -56,6 +358,25 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
                 return Ok(ValueType::String(active_window.class));
             }
         }
+        "current_device" => {
+            let device = match &amb.device {
+                Some(device) => device,
+                None => return Ok(ValueType::Void),
+            };
+
+            let value = match parsed_args.get(0) {
+                None => device.name.clone(),
+                Some(ValueType::String(property)) => match &**property {
+                    "name" => device.name.clone(),
+                    "path" => device.path.clone(),
+                    "id" => device.id(),
+                    _ => return Err(anyhow!("unknown device property '{}'", property)),
+                }
+                Some(_) => return Err(anyhow!("function 'current_device' expects a string argument")),
+            };
+
+            return Ok(ValueType::String(value));
+        }
         "on_window_change" => {
             if args.len() != 1 {
                 return Err(anyhow!("function takes 1 argument"));
@@ -130,6 +451,11 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
                 (ValueType::String(from), ValueType::Lambda(_, to, var_map)) => (from, (to, var_map)),
                 _ => return Err(anyhow!("invalid arguments passed to 'map_key'")),
             };
+            let options = match parsed_args.get(2) {
+                None => MappingOptions::default(),
+                Some(ValueType::String(options)) => MappingOptions::from_names(options)?,
+                Some(_) => return Err(anyhow!("the options passed to 'map_key' must be a string")),
+            };
 
             let mappings = match parse_key_action_with_mods(&*from, to.0).unwrap() {
                 Expr::KeyMapping(v) => v,
@@ -140,10 +466,261 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
                 let mapping = mapping.clone();
 
                 amb.message_tx.borrow_mut().as_ref().unwrap()
-                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, mapping.from, mapping.to, to.1.clone())).await
+                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, amb.layer.clone(), amb.condition.clone(), mapping.from, mapping.to, to.1.clone(), options)).await
                     .unwrap();
             }
         }
+        "sticky_modifiers" => {
+            let names = match parsed_args.get(0) {
+                Some(ValueType::String(names)) => names.clone(),
+                _ => return Err(anyhow!("function 'sticky_modifiers' expects a string argument")),
+            };
+
+            let mut modifiers = KeyModifierFlags::new();
+            for name in names.split_whitespace() {
+                match name {
+                    "ctrl" => modifiers.ctrl(),
+                    "shift" => modifiers.shift(),
+                    "alt" => modifiers.alt(),
+                    "meta" => modifiers.meta(),
+                    _ => return Err(anyhow!("unknown modifier '{}'", name)),
+                }
+            }
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetStickyModifiers(modifiers)).await.unwrap();
+        }
+        "caps_word" => {
+            let active = match parsed_args.get(0) {
+                None => true,
+                Some(ValueType::Bool(active)) => *active,
+                Some(_) => return Err(anyhow!("function 'caps_word' expects a boolean argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetCapsWord(active)).await.unwrap();
+        }
+        "auto_shift" => {
+            let active = match parsed_args.get(0) {
+                None => true,
+                Some(ValueType::Bool(active)) => *active,
+                Some(_) => return Err(anyhow!("function 'auto_shift' expects a boolean argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetAutoShift(active)).await.unwrap();
+        }
+        "auto_shift_timeout" => {
+            let timeout = match parsed_args.get(0) {
+                Some(ValueType::Number(timeout)) if *timeout >= 0.0 => *timeout as u64,
+                _ => return Err(anyhow!("function 'auto_shift_timeout' expects a positive number argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetAutoShiftTimeout(timeout)).await.unwrap();
+        }
+        "auto_shift_exclude" => {
+            let names = match parsed_args.get(0) {
+                Some(ValueType::String(names)) => names.clone(),
+                _ => return Err(anyhow!("function 'auto_shift_exclude' expects a string argument")),
+            };
+
+            let keys = names.split_whitespace().map(parse_key).collect::<Result<Vec<_>>>()?;
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::ExcludeFromAutoShift(keys)).await.unwrap();
+        }
+        "macro_record_start" => {
+            let name = match parsed_args.get(0) {
+                Some(ValueType::String(name)) => name.clone(),
+                _ => return Err(anyhow!("function 'macro_record_start' expects a macro name argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::StartMacroRecording(name)).await.unwrap();
+        }
+        "macro_record_stop" => {
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::StopMacroRecording(amb.trigger_key)).await.unwrap();
+        }
+        "macro_play" | "macro_save" => {
+            let macro_name = match parsed_args.get(0) {
+                Some(ValueType::String(macro_name)) => macro_name.clone(),
+                _ => return Err(anyhow!("function '{}' expects a macro name argument", name)),
+            };
+
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetMacro(macro_name.clone(), tx)).await.unwrap();
+            let events = rx.recv().await.unwrap().ok_or_else(|| anyhow!("macro '{}' not found", macro_name))?;
+
+            if &**name == "macro_save" {
+                save_macro(&macro_name, &events)?;
+                return Ok(ValueType::Void);
+            }
+
+            let speed = match parsed_args.get(1) {
+                None => 1.0,
+                Some(ValueType::Number(speed)) if *speed > 0.0 => *speed,
+                _ => return Err(anyhow!("the speed passed to 'macro_play' must be a positive number")),
+            };
+
+            // the events were recorded before the mappings, they're replayed as if they were typed on the
+            // device that triggered the mapping
+            let device = amb.device.clone().unwrap_or_else(|| Arc::new(DeviceInfo {
+                path: String::new(),
+                name: "macro".to_string(),
+                vendor: 0,
+                product: 0,
+                abs_ranges: vec![],
+                gamepad: false,
+            }));
+            for (delay, ev) in events.iter() {
+                tokio::time::sleep(delay.div_f64(speed)).await;
+                amb.message_tx.as_ref().unwrap()
+                    .send(ExecutionMessage::PlayMacroEvent(device.clone(), ev.clone())).await.unwrap();
+            }
+        }
+        "macro_load" => {
+            let name = match parsed_args.get(0) {
+                Some(ValueType::String(name)) => name.clone(),
+                _ => return Err(anyhow!("function 'macro_load' expects a macro name argument")),
+            };
+
+            let events = load_macro(&name)?;
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetMacro(name, Arc::new(events))).await.unwrap();
+        }
+        "turbo_start" | "turbo_toggle" => {
+            let (sequence, rate) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(sequence)), Some(ValueType::Number(rate))) if *rate > 0.0 => (sequence.clone(), *rate),
+                _ => return Err(anyhow!("function '{}' expects a key sequence and a positive rate", name)),
+            };
+            let jitter = match parsed_args.get(2) {
+                None => 0.0,
+                Some(ValueType::Number(jitter)) if *jitter >= 0.0 => *jitter,
+                Some(_) => return Err(anyhow!("the jitter passed to '{}' must be a positive number", name)),
+            };
+
+            let actions = parse_key_sequence(&sequence)?;
+            let interval = time::Duration::try_from_secs_f64(1.0 / rate)
+                .map_err(|_| anyhow!("the rate passed to '{}' is too low", name))?;
+            let jitter = time::Duration::try_from_secs_f64(jitter / 1000.0)
+                .map_err(|_| anyhow!("the jitter passed to '{}' is too long", name))?;
+            let msg = match &**name {
+                "turbo_start" => ExecutionMessage::StartTurbo(sequence, actions, interval, jitter),
+                _ => ExecutionMessage::ToggleTurbo(sequence, actions, interval, jitter),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(msg).await.unwrap();
+        }
+        "turbo_stop" => {
+            let sequence = match parsed_args.get(0) {
+                None => None,
+                Some(ValueType::String(sequence)) => Some(sequence.clone()),
+                Some(_) => return Err(anyhow!("function 'turbo_stop' expects a key sequence argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::StopTurbo(sequence)).await.unwrap();
+        }
+        "debounce" => {
+            let threshold = match parsed_args.get(0) {
+                Some(ValueType::Number(threshold)) if *threshold >= 0.0 => time::Duration::from_millis(*threshold as u64),
+                _ => return Err(anyhow!("function 'debounce' expects a positive threshold")),
+            };
+            let keys = match parsed_args.get(1) {
+                None => vec![],
+                Some(ValueType::String(keys)) => keys.split_whitespace().map(parse_key).collect::<Result<Vec<_>>>()?,
+                Some(_) => return Err(anyhow!("the keys passed to 'debounce' must be a string")),
+            };
+
+            // the threshold is scoped to the devices matched by the surrounding 'when' statements
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::SetDebounce(amb.condition.clone(), keys, threshold)).await.unwrap();
+        }
+        "event_value" => {
+            let value = amb.trigger_value.ok_or_else(|| anyhow!("'{}' can only be called from a mapping", name))?;
+            return Ok(ValueType::Number(value as f64));
+        }
+        "debounce_count" => {
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetDebounceCount(tx)).await.unwrap();
+            return Ok(ValueType::Number(rx.recv().await.unwrap() as f64));
+        }
+        "tap_dance" | "tap_dance_hold" => {
+            let (key, count, (block, inner_var_map)) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(key)), Some(ValueType::Number(count)), Some(ValueType::Lambda(_, block, var_map))) =>
+                    (key.clone(), *count, (block.clone(), var_map.clone())),
+                _ => return Err(anyhow!("invalid arguments passed to '{}'", name)),
+            };
+            if count < 1.0 { return Err(anyhow!("the tap count passed to '{}' must be at least 1", name)); }
+
+            let key = parse_key(&key)?;
+            let action = match &**name {
+                "tap_dance" => TapDanceAction::Tap(count as u32),
+                _ => TapDanceAction::Hold(count as u32),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::AddTapDance(key, action, block, inner_var_map)).await.unwrap();
+        }
+        "tap_dance_timeout" => {
+            let timeout = match parsed_args.get(0) {
+                Some(ValueType::Number(timeout)) if *timeout >= 0.0 => *timeout as u64,
+                _ => return Err(anyhow!("function 'tap_dance_timeout' expects a positive number argument")),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetTapDanceTimeout(timeout)).await.unwrap();
+        }
+        "on_release" => {
+            let (block, inner_var_map) = match parsed_args.get(0) {
+                Some(ValueType::Lambda(_, block, var_map)) => (block.clone(), var_map.clone()),
+                _ => return Err(anyhow!("function 'on_release' expects a lambda argument")),
+            };
+
+            match &amb.invocation {
+                Some(invocation) => invocation.on_release(block, inner_var_map),
+                None => return Err(anyhow!("function 'on_release' can only be used in mappings triggered by a key press")),
+            }
+        }
+        "layer" => {
+            let (name, (block, inner_var_map)) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(name)), Some(ValueType::Lambda(_, block, var_map))) => (name.clone(), (block.clone(), var_map.clone())),
+                _ => return Err(anyhow!("invalid arguments passed to 'layer'")),
+            };
+
+            // mappings defined inside of the callback are added to the layer
+            let previous_layer = amb.layer.replace(name);
+            eval_block(&block, &inner_var_map, amb).await;
+            amb.layer = previous_layer;
+        }
+        "layer_on" | "layer_off" | "layer_toggle" | "layer_oneshot" | "layer_while_held" => {
+            let layer_name = match parsed_args.get(0) {
+                Some(ValueType::String(layer_name)) => layer_name.clone(),
+                _ => return Err(anyhow!("function '{}' expects a layer name argument", name)),
+            };
+
+            let msg = match &**name {
+                "layer_on" => ExecutionMessage::ActivateLayer(layer_name, LayerActivation::Toggle),
+                "layer_off" => ExecutionMessage::DeactivateLayer(layer_name),
+                "layer_toggle" => ExecutionMessage::ToggleLayer(layer_name),
+                "layer_oneshot" => ExecutionMessage::ActivateLayer(layer_name, LayerActivation::OneShot),
+                "layer_while_held" => {
+                    let key = amb.trigger_key
+                        .ok_or_else(|| anyhow!("function 'layer_while_held' can only be used inside of key mappings"))?;
+                    ExecutionMessage::ActivateLayer(layer_name, LayerActivation::WhileHeld(key))
+                }
+                _ => unreachable!(),
+            };
+
+            amb.message_tx.as_ref().unwrap().send(msg).await.unwrap();
+        }
+        "active_layers" => {
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetActiveLayers(tx)).await.unwrap();
+            let layers = rx.recv().await.unwrap();
+            return Ok(ValueType::String(layers.join(",")));
+        }
+        "is_layer_active" => {
+            let layer_name = match parsed_args.get(0) {
+                Some(ValueType::String(layer_name)) => layer_name.clone(),
+                _ => return Err(anyhow!("function 'is_layer_active' expects a layer name argument")),
+            };
+
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetActiveLayers(tx)).await.unwrap();
+            let layers = rx.recv().await.unwrap();
+            return Ok(ValueType::Bool(layers.contains(&layer_name)));
+        }
         "execute" => {
             if parsed_args.len() < 1 { return Err(anyhow!("argument error: function 'execute' expected at least 1 argument")); }
 
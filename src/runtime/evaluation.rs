@@ -2,6 +2,8 @@ use std::borrow::BorrowMut;
 use std::fmt;
 use std::fmt::Formatter;
 
+use regex::Regex;
+
 use messaging::*;
 
 use crate::*;
@@ -9,9 +11,96 @@ use crate::*;
 use super::builtin_functions::evaluate_builtin;
 use super::builtin_functions::throw_error;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub(crate) struct KeyActionCondition {
-    pub(crate) window_class_name: Option<String>,
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConditionProperty {
+    WindowClass,
+    WindowInstance,
+    WindowTitle,
+    DeviceName,
+    DevicePath,
+    DeviceId,
+}
+
+/// A regex that needs to match the whole property.
+#[derive(Debug, Clone)]
+pub struct ConditionPattern(Regex);
+
+impl ConditionPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Regex::new(&format!("^(?:{})$", pattern))
+            .map(ConditionPattern)
+            .map_err(|err| anyhow!("invalid condition pattern '{}': {}", pattern, err))
+    }
+
+    pub fn is_match(&self, value: &str) -> bool { self.0.is_match(value) }
+}
+
+impl PartialEq for ConditionPattern {
+    fn eq(&self, other: &Self) -> bool { self.0.as_str() == other.0.as_str() }
+}
+
+impl Eq for ConditionPattern {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct KeyActionCondition {
+    pub window_class: Option<ConditionPattern>,
+    pub window_instance: Option<ConditionPattern>,
+    pub window_title: Option<ConditionPattern>,
+    pub device_name: Option<ConditionPattern>,
+    pub device_path: Option<ConditionPattern>,
+    pub device_id: Option<ConditionPattern>,
+}
+
+impl KeyActionCondition {
+    pub fn new() -> Self {
+        KeyActionCondition {
+            window_class: None,
+            window_instance: None,
+            window_title: None,
+            device_name: None,
+            device_path: None,
+            device_id: None,
+        }
+    }
+
+    pub fn set(&mut self, property: ConditionProperty, pattern: ConditionPattern) {
+        match property {
+            ConditionProperty::WindowClass => self.window_class = Some(pattern),
+            ConditionProperty::WindowInstance => self.window_instance = Some(pattern),
+            ConditionProperty::WindowTitle => self.window_title = Some(pattern),
+            ConditionProperty::DeviceName => self.device_name = Some(pattern),
+            ConditionProperty::DevicePath => self.device_path = Some(pattern),
+            ConditionProperty::DeviceId => self.device_id = Some(pattern),
+        }
+    }
+
+    pub fn is_satisfied(&self, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>) -> bool {
+        fn matches(pattern: &Option<ConditionPattern>, value: Option<&str>) -> bool {
+            match (pattern, value) {
+                (None, _) => true,
+                (Some(pattern), Some(value)) => pattern.is_match(value),
+                (Some(_), None) => false,
+            }
+        }
+
+        if self.specificity() == 0 { return true; }
+
+        matches(&self.window_class, window.map(|w| &*w.class))
+            && matches(&self.window_instance, window.map(|w| &*w.instance))
+            && matches(&self.window_title, window.map(|w| &*w.name))
+            && matches(&self.device_name, device.map(|d| &*d.name))
+            && matches(&self.device_path, device.map(|d| &*d.path))
+            // the id is formatted, so it's only computed if needed
+            && (self.device_id.is_none() || matches(&self.device_id, device.map(|d| d.id()).as_deref()))
+    }
+
+    /// The number of constraints, mappings with more constraints take precedence.
+    pub fn specificity(&self) -> usize {
+        [&self.window_class, &self.window_instance, &self.window_title,
+            &self.device_name, &self.device_path, &self.device_id].iter()
+            .filter(|v| v.is_some())
+            .count()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -189,7 +278,7 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
                 let mapping = mapping.clone();
 
                 amb.message_tx.borrow_mut().as_ref().unwrap()
-                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, mapping.from, mapping.to, var_map.clone())).await
+                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, amb.layer.clone(), amb.condition.clone(), mapping.from, mapping.to, var_map.clone(), MappingOptions::default())).await
                     .unwrap();
             }
 
@@ -229,8 +318,8 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
             return ValueType::Lambda(params.clone(), block.clone(), lambda_var_map);
         }
         Expr::KeyAction(action) => {
-            amb.ev_writer_tx.send(action.to_input_ev()).await.unwrap();
-            amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
+            amb.ev_writer_tx.send(action.to_input_ev().into()).await.unwrap();
+            amb.ev_writer_tx.send(SYN_REPORT.clone().into()).await.unwrap();
 
             return ValueType::Void;
         }
@@ -262,31 +351,32 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
                 if *to_type == 1 { // restore mods if actual mod is still pressed
                     if *is_actual_down {
                         futures::executor::block_on(amb.ev_writer_tx.send(
-                            KeyAction { key: *key, value: *to_type }.to_input_ev()
+                            KeyAction { key: *key, value: *to_type }.to_input_ev().into()
                         )).unwrap();
                     }
                 } else { // release mods if actual mod is still pressed (prob. always true since it was necessary to trigger the mapping)
                     if *is_actual_down != false {
                         futures::executor::block_on(amb.ev_writer_tx.send(
-                            KeyAction { key: *key, value: *to_type }.to_input_ev()
+                            KeyAction { key: *key, value: *to_type }.to_input_ev().into()
                         )).unwrap();
                     }
                 }
             };
 
-            if from_flags.ctrl && !to_flags.ctrl {
+            // wildcard triggers release whatever modifiers are held when the mapping is triggered
+            if (from_flags.ctrl || from_flags.wildcard) && !to_flags.ctrl {
                 release_or_restore_modifier(&actual_state.left_ctrl, &*KEY_LEFT_CTRL);
                 release_or_restore_modifier(&actual_state.right_ctrl, &*KEY_RIGHT_CTRL);
             }
-            if from_flags.shift && !to_flags.shift {
+            if (from_flags.shift || from_flags.wildcard) && !to_flags.shift {
                 release_or_restore_modifier(&actual_state.left_shift, &*KEY_LEFT_SHIFT);
                 release_or_restore_modifier(&actual_state.right_shift, &*KEY_RIGHT_SHIFT);
             }
-            if from_flags.alt && !to_flags.alt {
+            if (from_flags.alt || from_flags.wildcard) && !to_flags.alt {
                 release_or_restore_modifier(&actual_state.left_alt, &*KEY_LEFT_ALT);
                 release_or_restore_modifier(&actual_state.right_alt, &*KEY_RIGHT_ALT);
             }
-            if from_flags.meta && !to_flags.meta {
+            if (from_flags.meta || from_flags.wildcard) && !to_flags.meta {
                 release_or_restore_modifier(&actual_state.left_meta, &*KEY_LEFT_META);
                 release_or_restore_modifier(&actual_state.right_meta, &*KEY_RIGHT_META);
             }
@@ -301,10 +391,22 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
 pub type SleepSender = tokio::sync::mpsc::Sender<Block>;
 
 pub struct Ambient<'a> {
-    pub ev_writer_tx: mpsc::Sender<InputEvent>,
+    pub ev_writer_tx: mpsc::Sender<OutputEvent>,
     pub message_tx: Option<&'a mut ExecutionMessageSender>,
     pub window_cycle_token: usize,
     pub modifier_state: &'a KeyModifierState,
+    // the layer new mappings are added to, global mappings are used if not set
+    pub layer: Option<String>,
+    // the condition new mappings are added with
+    pub condition: KeyActionCondition,
+    // the physical key that triggered the current mapping
+    pub trigger_key: Option<Key>,
+    // the value of the event that triggered the current mapping, i.e. the distance of a wheel event
+    pub trigger_value: Option<i32>,
+    // the device that triggered the current mapping
+    pub device: Option<Arc<DeviceInfo>>,
+    // the key press that triggered the current mapping
+    pub invocation: Option<KeyPressInvocation>,
 }
 
 pub enum BlockRet {
@@ -346,6 +448,31 @@ pub async fn eval_block<'a>(block: &Block, var_map: &GuardedVarMap, amb: &mut Am
                     };
                 }
             }
+            Stmt::When(conditions, block) => {
+                let mut condition = amb.condition.clone();
+                for (property, expr) in conditions {
+                    let pattern = match eval_expr(expr, &var_map, amb).await {
+                        ValueType::String(pattern) => ConditionPattern::new(&pattern),
+                        _ => Err(anyhow!("conditions expect a string argument")),
+                    };
+                    match pattern {
+                        Ok(pattern) => condition.set(*property, pattern),
+                        Err(err) => {
+                            throw_error(err, 1, amb).await;
+                            return BlockRet::None;
+                        }
+                    }
+                }
+
+                // mappings defined inside of the block only apply while the condition is satisfied
+                let previous_condition = std::mem::replace(&mut amb.condition, condition);
+                let ret = eval_block(block, &mut var_map, amb).await;
+                amb.condition = previous_condition;
+                match ret {
+                    BlockRet::None => {}
+                    _ => return ret,
+                };
+            }
             Stmt::For(init_expr, termination_expr, advance_expr, block) => {
                 eval_expr(init_expr, &var_map, amb).await;
 
@@ -356,6 +483,9 @@ pub async fn eval_block<'a>(block: &Block, var_map: &GuardedVarMap, amb: &mut Am
                     };
                     if !should_continue { break; }
 
+                    // stop looping once the mapping was cancelled by releasing its key
+                    if amb.invocation.as_ref().map_or(false, |invocation| invocation.is_cancelled()) { break; }
+
                     let ret = eval_block(block, &mut var_map, amb).await;
                     match ret {
                         BlockRet::Return(_) => return ret,
@@ -432,6 +562,7 @@ pub(crate) enum Stmt {
     Block(Block),
     If(Vec<(Expr, Block)>, Option<Block>),
     For(Expr, Expr, Expr, Block),
+    When(Vec<(ConditionProperty, Expr)>, Block),
     // While
     Return(Expr),
     Continue,
@@ -27,7 +27,7 @@ pub fn parse_script(script_file: &mut fs::File) -> Block {
 pub async fn evaluate_script(
     script_ast: Block,
     mut execution_message_tx: mpsc::Sender<ExecutionMessage>,
-    ev_reader_tx: mpsc::Sender<InputEvent>,
+    ev_reader_tx: mpsc::Sender<OutputEvent>,
     window_cycle_token: usize,
 ) {
     let mut amb = Ambient {
@@ -35,6 +35,12 @@ pub async fn evaluate_script(
         window_cycle_token,
         message_tx: Some(&mut execution_message_tx),
         modifier_state: &KeyModifierState::new(),
+        layer: None,
+        condition: KeyActionCondition::new(),
+        trigger_key: None,
+        trigger_value: None,
+        device: None,
+        invocation: None,
     };
 
     eval_block(&script_ast, &mut GuardedVarMap::new(Mutex::new(VarMap::new(None))), &mut amb).await;
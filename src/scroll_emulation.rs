@@ -0,0 +1,203 @@
+use crate::*;
+
+// the distance the pointer has to travel before it scrolls and the scroll axis is locked, shorter movements
+// (i.e. sensor jitter) still click the trigger
+const SCROLL_DISTANCE: i32 = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+/// Turns pointer movement into scrolling while a key or mouse button is held.
+///
+/// The trigger is held back until it's released, if the pointer barely moved in the meantime it's clicked
+/// along with the held back movement so it keeps its original function. With axis lock enabled, only the
+/// axis the pointer moved along first is scrolled.
+pub struct ScrollEmulation {
+    trigger: Option<Key>,
+    // the movement in pixels that scrolls one notch
+    sensitivity: f64,
+    axis_lock: bool,
+    natural: bool,
+    held: bool,
+    // the distance the pointer traveled on each axis since the trigger was pressed
+    travel: (i32, i32),
+    // the movement held back until the pointer traveled far enough to scroll
+    moved: (i32, i32),
+    scrolling: bool,
+    locked_axis: Option<Axis>,
+    wheel: (WheelAccumulator, WheelAccumulator),
+}
+
+impl ScrollEmulation {
+    pub fn new() -> Self {
+        ScrollEmulation {
+            trigger: None,
+            sensitivity: 20.0,
+            axis_lock: true,
+            natural: false,
+            held: false,
+            travel: (0, 0),
+            moved: (0, 0),
+            scrolling: false,
+            locked_axis: None,
+            wheel: Default::default(),
+        }
+    }
+
+    /// Sets the key or button that needs to be held for scrolling, scroll emulation is disabled if none is given.
+    pub fn set_trigger(&mut self, trigger: Option<Key>) {
+        self.trigger = trigger;
+        self.held = false;
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f64) { self.sensitivity = sensitivity; }
+
+    pub fn set_axis_lock(&mut self, axis_lock: bool) { self.axis_lock = axis_lock; }
+
+    pub fn set_natural(&mut self, natural: bool) { self.natural = natural; }
+
+    /// Gets the events an input event turned into.
+    pub fn handle_event(&mut self, ev: &InputEvent) -> Vec<InputEvent> {
+        let trigger = match self.trigger {
+            Some(trigger) => trigger,
+            None => return vec![ev.clone()],
+        };
+
+        if ev.event_code == trigger.event_code {
+            if ev.value == TYPE_DOWN {
+                self.held = true;
+                self.travel = (0, 0);
+                self.moved = (0, 0);
+                self.scrolling = false;
+                self.locked_axis = None;
+                self.wheel = Default::default();
+                return vec![];
+            }
+            if !self.held { return vec![ev.clone()]; }
+            if ev.value != TYPE_UP { return vec![]; }
+
+            self.held = false;
+            if self.scrolling { return vec![]; }
+
+            // the pointer barely moved, click the trigger
+            let mut events = move_events(self.moved.0, self.moved.1);
+            events.extend(vec![KeyAction::new(trigger, TYPE_DOWN).to_input_ev(), SYN_REPORT.clone(), ev.clone()]);
+            return events;
+        }
+
+        if !self.held { return vec![ev.clone()]; }
+
+        let axis = match ev.event_code {
+            EventCode::EV_REL(_) if ev.event_code == REL_Y.event_code => Axis::Vertical,
+            EventCode::EV_REL(_) if ev.event_code == REL_X.event_code => Axis::Horizontal,
+            _ => return vec![ev.clone()],
+        };
+
+        match axis {
+            Axis::Vertical => self.travel.1 += ev.value.abs(),
+            Axis::Horizontal => self.travel.0 += ev.value.abs(),
+        }
+
+        if !self.scrolling {
+            if self.travel.0.max(self.travel.1) < SCROLL_DISTANCE {
+                match axis {
+                    Axis::Vertical => self.moved.1 += ev.value,
+                    Axis::Horizontal => self.moved.0 += ev.value,
+                }
+                return vec![];
+            }
+            self.scrolling = true;
+            self.locked_axis = Some(if self.travel.1 >= self.travel.0 { Axis::Vertical } else { Axis::Horizontal });
+        }
+        if self.axis_lock && self.locked_axis != Some(axis) { return vec![]; }
+
+        // moving the pointer down scrolls down, natural scrolling moves the content along with the pointer instead
+        let direction = if self.natural { 1.0 } else { -1.0 };
+        let units = ev.value as f64 * WHEEL_HI_RES_UNITS / self.sensitivity;
+        match axis {
+            Axis::Vertical => self.wheel.0.scroll(*REL_WHEEL, *REL_WHEEL_HI_RES, direction * units),
+            Axis::Horizontal => self.wheel.1.scroll(*REL_HWHEEL, *REL_HWHEEL_HI_RES, -direction * units),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn scroll_emulation() -> ScrollEmulation {
+        let mut scroll_emulation = ScrollEmulation::new();
+        scroll_emulation.set_trigger(Some(*BTN_MIDDLE));
+        scroll_emulation
+    }
+
+    #[test]
+    fn test_click_without_movement() {
+        let mut scroll_emulation = scroll_emulation();
+        assert_eq!(scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_DOWN)), vec![]);
+        assert_eq!(scroll_emulation.handle_event(&SYN_REPORT.clone()), vec![SYN_REPORT.clone()]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_UP)), vec![
+            ev(*BTN_MIDDLE, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*BTN_MIDDLE, TYPE_UP),
+        ]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, 10)), vec![ev(*REL_Y, 10)]);
+    }
+
+    #[test]
+    fn test_click_with_jitter() {
+        let mut scroll_emulation = scroll_emulation();
+        scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_DOWN));
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_X, 2)), vec![]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, -1)), vec![]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_X, -1)), vec![]);
+
+        // the held back movement is replayed along with the click
+        assert_eq!(scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_UP)), vec![
+            ev(*REL_X, 1),
+            ev(*REL_Y, -1),
+            SYN_REPORT.clone(),
+            ev(*BTN_MIDDLE, TYPE_DOWN),
+            SYN_REPORT.clone(),
+            ev(*BTN_MIDDLE, TYPE_UP),
+        ]);
+    }
+
+    #[test]
+    fn test_axis_lock() {
+        let mut scroll_emulation = scroll_emulation();
+        scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_DOWN));
+
+        // the axis is locked once the pointer traveled far enough
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, 2)), vec![]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, 3)), vec![ev(*REL_WHEEL_HI_RES, -18)]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_X, 10)), vec![]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, 20)), vec![
+            ev(*REL_WHEEL_HI_RES, -120),
+            ev(*REL_WHEEL, -1),
+        ]);
+
+        // the trigger isn't clicked after scrolling
+        assert_eq!(scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_UP)), vec![]);
+    }
+
+    #[test]
+    fn test_natural_scrolling() {
+        let mut scroll_emulation = scroll_emulation();
+        scroll_emulation.set_axis_lock(false);
+        scroll_emulation.set_natural(true);
+        scroll_emulation.handle_event(&ev(*BTN_MIDDLE, TYPE_DOWN));
+
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_Y, 20)), vec![
+            ev(*REL_WHEEL_HI_RES, 120),
+            ev(*REL_WHEEL, 1),
+        ]);
+        assert_eq!(scroll_emulation.handle_event(&ev(*REL_X, 1)), vec![ev(*REL_HWHEEL_HI_RES, -6)]);
+    }
+}
@@ -1,17 +1,179 @@
+use tokio::sync::watch;
+
 use ignore_list::*;
 
 use crate::*;
 
+pub type CompiledKeyMapping = Arc<(Block, GuardedVarMap, MappingOptions)>;
+
+type ConditionalMappings = Vec<(KeyActionCondition, CompiledKeyMapping)>;
+
+/// The mappings indexed by the key and value of their trigger, so only the triggers of a key are matched against
+/// the held modifiers.
 #[derive(Clone, Debug)]
-pub struct CompiledKeyMappings(pub HashMap<KeyActionWithMods, Arc<(Block, GuardedVarMap)>>);
+pub struct CompiledKeyMappings(pub HashMap<(Key, i32), Vec<(KeyModifierFlags, ConditionalMappings)>>);
+
+impl CompiledKeyMappings {
+    pub fn new() -> Self { CompiledKeyMappings(Default::default()) }
+
+    pub fn insert(&mut self, from: KeyActionWithMods, condition: KeyActionCondition, mapping: CompiledKeyMapping) {
+        let triggers = self.0.entry((from.key, from.value)).or_insert_with(Vec::new);
+        let idx = match triggers.iter().position(|(modifiers, _)| *modifiers == from.modifiers) {
+            Some(idx) => idx,
+            None => {
+                triggers.push((from.modifiers, vec![]));
+                triggers.len() - 1
+            }
+        };
+
+        let candidates = &mut triggers[idx].1;
+        match candidates.iter_mut().find(|(v, _)| *v == condition) {
+            Some(candidate) => candidate.1 = mapping,
+            None => candidates.push((condition, mapping)),
+        }
+    }
+
+    /// Gets the most specific mapping whose condition is satisfied by the active window and input device.
+    /// Wildcard triggers are only considered if there is no exact match.
+    pub fn get(&self, from: &KeyActionWithMods, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>)
+               -> Option<&CompiledKeyMapping> {
+        self.get_matching(from, window, device).map(|(_, mapping)| mapping)
+    }
+
+    /// Gets the most specific trigger matching an action along with its mapping.
+    fn get_matching(&self, from: &KeyActionWithMods, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>)
+                    -> Option<(KeyActionWithMods, &CompiledKeyMapping)> {
+        self.0.get(&(from.key, from.value))?.iter()
+            .filter_map(|(modifiers, candidates)| {
+                let rank = modifiers.match_rank(&from.modifiers)?;
+                let mapping = Self::get_satisfied(candidates, window, device)?;
+                Some((rank, KeyActionWithMods { modifiers: *modifiers, ..*from }, mapping))
+            })
+            .min_by_key(|(rank, _, _)| *rank)
+            .map(|(_, trigger, mapping)| (trigger, mapping))
+    }
+
+    fn get_exact(&self, trigger: &KeyActionWithMods, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>)
+                 -> Option<&CompiledKeyMapping> {
+        let (_, candidates) = self.0.get(&(trigger.key, trigger.value))?.iter()
+            .find(|(modifiers, _)| *modifiers == trigger.modifiers)?;
+        Self::get_satisfied(candidates, window, device)
+    }
+
+    fn get_satisfied<'a>(candidates: &'a ConditionalMappings, window: Option<&ActiveWindowInfo>,
+                         device: Option<&DeviceInfo>) -> Option<&'a CompiledKeyMapping> {
+        candidates.iter()
+            .filter(|(condition, _)| condition.is_satisfied(window, device))
+            .max_by_key(|(condition, _)| condition.specificity())
+            .map(|(_, mapping)| mapping)
+    }
+
+    /// Gets the mapping for a key press along with the mappings for the release and repeat events of the same
+    /// trigger, so they can be handled by the same mapping even if the held modifiers change in between.
+    pub fn get_invocation(&self, from: &KeyActionWithMods, window: Option<&ActiveWindowInfo>, device: Option<&DeviceInfo>)
+                          -> Option<(CompiledKeyMapping, ActiveInvocation)> {
+        let (trigger, mapping) = self.get_matching(from, window, device)?;
+        let with_value = |value| KeyActionWithMods { value, ..trigger };
+        let invocation = ActiveInvocation {
+            up: self.get_exact(&with_value(TYPE_UP), window, device).cloned(),
+            repeat: self.get_exact(&with_value(TYPE_REPEAT), window, device).cloned(),
+            release_tx: None,
+        };
+        Some((mapping.clone(), invocation))
+    }
+}
+
+/// The mappings that handle the release and repeat events of a key whose press triggered a mapping, events
+/// without a mapping are passed through.
+pub struct ActiveInvocation {
+    pub up: Option<CompiledKeyMapping>,
+    pub repeat: Option<CompiledKeyMapping>,
+    // releases the invocation of the press mapping
+    pub release_tx: Option<watch::Sender<bool>>,
+}
+
+/// Keys that are used as modifiers in mapping triggers, i.e. 'capslock' in 'capslock & h::left'.
+///
+/// Custom modifiers are not sent to the output while held, releasing one that wasn't combined with another key
+/// taps it instead. A key only acts as a custom modifier where a mapping using it applies, that is while its
+/// layer is active and its condition is satisfied.
+pub struct CustomModifiers {
+    // the layers and conditions of the mappings that use a key as a custom modifier
+    declared: HashMap<Key, Vec<(Option<String>, KeyActionCondition)>>,
+    // held custom modifiers in the order they were pressed, flagged once they were combined with another key
+    held: Vec<(Key, bool)>,
+}
+
+impl CustomModifiers {
+    pub fn new() -> Self { CustomModifiers { declared: Default::default(), held: vec![] } }
+
+    pub fn declare(&mut self, key: Key, layer: Option<String>, condition: KeyActionCondition) {
+        let scopes = self.declared.entry(key).or_insert_with(Vec::new);
+        if !scopes.iter().any(|(l, c)| *l == layer && *c == condition) { scopes.push((layer, condition)); }
+    }
+
+    /// Whether a key acts as a custom modifier with the active layers, window and input device.
+    pub fn is_declared(&self, key: &Key, layers: &Layers, window: Option<&ActiveWindowInfo>,
+                       device: Option<&DeviceInfo>) -> bool {
+        self.declared.get(key).map_or(false, |scopes| scopes.iter().any(|(layer, condition)| {
+            layer.as_ref().map_or(true, |layer| layers.is_active(layer)) && condition.is_satisfied(window, device)
+        }))
+    }
 
-impl CompiledKeyMappings { pub fn new() -> Self { CompiledKeyMappings(Default::default()) } }
+    pub fn is_held(&self, key: &Key) -> bool { self.held.iter().any(|(held_key, _)| held_key == key) }
+
+    /// The most recently pressed custom modifier that is still held.
+    pub fn active(&self) -> Option<Key> { self.held.last().map(|(key, _)| *key) }
+
+    /// Marks the held custom modifiers as combined with another key.
+    pub fn mark_used(&mut self) {
+        for (_, used) in self.held.iter_mut() { *used = true; }
+    }
+
+    pub fn press(&mut self, key: Key) {
+        if !self.is_held(&key) {
+            self.held.push((key, false));
+        }
+    }
+
+    /// Returns true if the key should be tapped since it wasn't combined with another key.
+    pub fn release(&mut self, key: &Key) -> bool {
+        match self.held.iter().position(|(held_key, _)| held_key == key) {
+            Some(idx) => !self.held.remove(idx).1,
+            None => false,
+        }
+    }
+}
 
 pub struct State {
     pub modifiers: Arc<KeyModifierState>,
 
     pub ignore_list: IgnoreList,
     pub active_window: Option<ActiveWindowInfo>,
+    pub layers: Layers,
+    pub custom_modifiers: CustomModifiers,
+    // the held keys by the path of their device, the same key can be held on several devices at once
+    pub active_invocations: HashMap<(String, Key), ActiveInvocation>,
+    pub executor: MappingExecutor,
+    pub sticky_modifiers: StickyModifiers,
+    // shifts letters until a non-word key is pressed
+    pub caps_word: bool,
+    pub tap_dances: TapDances,
+    pub auto_shift: AutoShift,
+    pub macros: Macros,
+    pub turbo: Turbo,
+    pub debounce: Debounce,
+    // the wheels scrolled by mappings
+    pub wheels: Wheels,
+    pub wheel_notches: WheelNotches,
+    pub mouse_keys: MouseKeys,
+    pub scroll_emulation: ScrollEmulation,
+    pub pointer_transform: PointerTransform,
+    pub gestures: Gestures,
+    pub abs_thresholds: AbsThresholds,
+    pub gamepad: Gamepad,
+    pub joystick_mouse: JoystickMouse,
+    pub leds: Leds,
 }
 
 
@@ -21,6 +183,84 @@ impl State {
             modifiers: Arc::new(KeyModifierState::new()),
             ignore_list: IgnoreList::new(),
             active_window: None,
+            layers: Layers::new(),
+            custom_modifiers: CustomModifiers::new(),
+            active_invocations: Default::default(),
+            executor: MappingExecutor::new(),
+            sticky_modifiers: StickyModifiers::new(),
+            caps_word: false,
+            tap_dances: TapDances::new(),
+            auto_shift: AutoShift::new(),
+            macros: Macros::new(),
+            turbo: Turbo::new(),
+            debounce: Debounce::new(),
+            wheels: Wheels::new(),
+            wheel_notches: WheelNotches::new(),
+            mouse_keys: MouseKeys::new(),
+            scroll_emulation: ScrollEmulation::new(),
+            pointer_transform: PointerTransform::new(),
+            gestures: Gestures::new(),
+            abs_thresholds: AbsThresholds::new(),
+            gamepad: Gamepad::new(),
+            joystick_mouse: JoystickMouse::new(),
+            leds: Leds::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn flags(f: impl Fn(&mut KeyModifierFlags)) -> KeyModifierFlags {
+        let mut flags = KeyModifierFlags::new();
+        f(&mut flags);
+        flags
+    }
+
+    #[test]
+    fn test_most_specific_trigger_wins() {
+        let any_ctrl = mapping();
+        let left_ctrl = mapping();
+        let wildcard = mapping();
+
+        let mut mappings = CompiledKeyMappings::new();
+        mappings.insert(KeyActionWithMods::new(*KEY_A, TYPE_DOWN, flags(|f| f.ctrl())), KeyActionCondition::new(), any_ctrl.clone());
+        mappings.insert(KeyActionWithMods::new(*KEY_A, TYPE_DOWN, flags(|f| f.wildcard())), KeyActionCondition::new(), wildcard.clone());
+
+        let held_left_ctrl = KeyActionWithMods::new(*KEY_A, TYPE_DOWN, flags(|f| { f.ctrl(); f.ctrl_side = ModifierSide::Left; }));
+        assert!(Arc::ptr_eq(mappings.get(&held_left_ctrl, None, None).unwrap(), &any_ctrl));
+
+        mappings.insert(held_left_ctrl, KeyActionCondition::new(), left_ctrl.clone());
+        assert!(Arc::ptr_eq(mappings.get(&held_left_ctrl, None, None).unwrap(), &left_ctrl));
+
+        // only the wildcard trigger accepts additional modifiers
+        let held_shift = KeyActionWithMods::new(*KEY_A, TYPE_DOWN, flags(|f| { f.ctrl(); f.shift(); }));
+        assert!(Arc::ptr_eq(mappings.get(&held_shift, None, None).unwrap(), &wildcard));
+
+        let held_right_ctrl = KeyActionWithMods::new(*KEY_A, TYPE_DOWN, flags(|f| { f.ctrl(); f.ctrl_side = ModifierSide::Right; }));
+        assert!(Arc::ptr_eq(mappings.get(&held_right_ctrl, None, None).unwrap(), &any_ctrl));
+        assert!(mappings.get(&KeyActionWithMods::new(*KEY_B, TYPE_DOWN, KeyModifierFlags::new()), None, None).is_none());
+    }
+
+    #[test]
+    fn test_custom_modifier_scope() {
+        let mut custom_modifiers = CustomModifiers::new();
+        let mut layers = Layers::new();
+        let mut condition = KeyActionCondition::new();
+        condition.set(ConditionProperty::DeviceName, ConditionPattern::new("Test Keyboard").unwrap());
+        custom_modifiers.declare(*KEY_CAPSLOCK, Some("nav".to_string()), KeyActionCondition::new());
+        custom_modifiers.declare(*KEY_TAB, None, condition);
+
+        // the key is only a custom modifier while the layer of its mapping is active
+        assert!(!custom_modifiers.is_declared(&KEY_CAPSLOCK, &layers, None, None));
+        layers.activate("nav".to_string(), LayerActivation::Toggle);
+        assert!(custom_modifiers.is_declared(&KEY_CAPSLOCK, &layers, None, None));
+
+        assert!(!custom_modifiers.is_declared(&KEY_TAB, &layers, None, None));
+        let device = device("/dev/input/event0", "Test Keyboard");
+        assert!(custom_modifiers.is_declared(&KEY_TAB, &layers, None, Some(&device)));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum StickyState {
+    // held, no other key was pressed yet
+    Pending,
+    // held and combined with another key, behaves like a regular modifier
+    Used,
+    // tapped once, applies to the next key
+    Latched,
+    // pressed again while latched
+    PendingLock,
+    // tapped twice, stays held until tapped again
+    Locked,
+    // pressed again while locked
+    PendingUnlock,
+}
+
+/// Modifiers that stay held after being tapped.
+///
+/// A tapped sticky modifier applies to the next key press only, tapping it twice locks it until it's tapped
+/// again. The modifier state is kept held while latched or locked, so mappings see the modifier as well.
+pub struct StickyModifiers {
+    enabled: KeyModifierFlags,
+    states: HashMap<Key, StickyState>,
+}
+
+impl StickyModifiers {
+    pub fn new() -> Self {
+        StickyModifiers { enabled: KeyModifierFlags::new(), states: Default::default() }
+    }
+
+    pub fn set_enabled(&mut self, enabled: KeyModifierFlags) { self.enabled = enabled; }
+
+    fn is_enabled(&self, key: &Key) -> bool {
+        if [*KEY_LEFT_CTRL, *KEY_RIGHT_CTRL].contains(key) { return self.enabled.ctrl; }
+        if [*KEY_LEFT_SHIFT, *KEY_RIGHT_SHIFT].contains(key) { return self.enabled.shift; }
+        if [*KEY_LEFT_ALT, *KEY_RIGHT_ALT].contains(key) { return self.enabled.alt; }
+        if [*KEY_LEFT_META, *KEY_RIGHT_META].contains(key) { return self.enabled.meta; }
+        false
+    }
+
+    /// Updates the state of a modifier key, returns false if the event should be swallowed since the modifier
+    /// is kept held.
+    pub fn handle_modifier_event(&mut self, key: Key, value: i32) -> bool {
+        let state = self.states.get(&key).copied();
+
+        // modifiers that are already latched or locked are handled even if they were disabled in the meantime
+        if state.is_none() && !self.is_enabled(&key) { return true; }
+
+        if value == TYPE_DOWN {
+            match state {
+                None => { self.states.insert(key, StickyState::Pending); }
+                Some(StickyState::Latched) => { self.states.insert(key, StickyState::PendingLock); }
+                Some(StickyState::Locked) => { self.states.insert(key, StickyState::PendingUnlock); }
+                _ => {}
+            }
+            state.is_none()
+        } else if value == TYPE_UP {
+            match state {
+                Some(StickyState::Pending) => { self.states.insert(key, StickyState::Latched); }
+                Some(StickyState::PendingLock) => { self.states.insert(key, StickyState::Locked); }
+                _ => { self.states.remove(&key); }
+            }
+            matches!(state, None | Some(StickyState::Used) | Some(StickyState::PendingUnlock))
+        } else {
+            matches!(state, None | Some(StickyState::Pending) | Some(StickyState::Used))
+        }
+    }
+
+    /// Updates the states on a non-modifier key press, returns the latched modifiers that need to be released
+    /// after the key.
+    pub fn handle_key_press(&mut self) -> Vec<Key> {
+        let mut released = vec![];
+        for (key, state) in self.states.iter_mut() {
+            match state {
+                StickyState::Pending | StickyState::PendingLock | StickyState::PendingUnlock => *state = StickyState::Used,
+                StickyState::Latched => released.push(*key),
+                _ => {}
+            }
+        }
+        for key in released.iter() { self.states.remove(key); }
+        released
+    }
+}
+
+/// Keys that don't end caps word.
+pub fn is_caps_word_key(key: &Key) -> bool {
+    matches!(key.char(), Some(c) if c.is_ascii_alphanumeric())
+        || [*KEY_MINUS, *KEY_BACKSPACE].contains(key)
+        || key.is_modifier()
+}
+
+/// Keys that are shifted while caps word is active.
+pub fn is_caps_word_shifted_key(key: &Key) -> bool {
+    matches!(key.char(), Some(c) if c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use tap::Tap;
+
+    use super::*;
+
+    fn sticky_shift() -> StickyModifiers {
+        let mut sticky = StickyModifiers::new();
+        sticky.set_enabled(KeyModifierFlags::new().tap_mut(|v| v.shift()));
+        sticky
+    }
+
+    #[test]
+    fn test_latch() {
+        let mut sticky = sticky_shift();
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_DOWN));
+        assert!(!sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_UP));
+        assert_eq!(sticky.handle_key_press(), vec![*KEY_LEFT_SHIFT]);
+        assert_eq!(sticky.handle_key_press(), vec![]);
+    }
+
+    #[test]
+    fn test_lock() {
+        let mut sticky = sticky_shift();
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_DOWN));
+        assert!(!sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_UP));
+        assert!(!sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_DOWN));
+        assert!(!sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_UP));
+        assert_eq!(sticky.handle_key_press(), vec![]);
+        assert!(!sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_DOWN));
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_UP));
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut sticky = sticky_shift();
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_DOWN));
+        assert_eq!(sticky.handle_key_press(), vec![]);
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_SHIFT, TYPE_UP));
+
+        // disabled modifiers aren't sticky
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_CTRL, TYPE_DOWN));
+        assert!(sticky.handle_modifier_event(*KEY_LEFT_CTRL, TYPE_UP));
+    }
+
+    #[test]
+    fn test_caps_word_keys() {
+        assert!(is_caps_word_shifted_key(&*KEY_A));
+        assert!(is_caps_word_key(&*KEY_MINUS));
+        assert!(!is_caps_word_shifted_key(&*KEY_MINUS));
+        assert!(!is_caps_word_key(&*KEY_SPACE));
+    }
+}
@@ -0,0 +1,335 @@
+use tokio::sync::watch;
+
+use crate::*;
+
+/// What a tap dance resolved to, the count includes the held press.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TapDanceAction {
+    Tap(u32),
+    Hold(u32),
+}
+
+struct TapDanceDefinition {
+    taps: HashMap<u32, CompiledKeyMapping>,
+    holds: HashMap<u32, CompiledKeyMapping>,
+}
+
+impl TapDanceDefinition {
+    // the dance resolves right away once no callback for more taps exists
+    fn max_count(&self) -> u32 {
+        self.taps.keys().chain(self.holds.keys()).copied().max().unwrap_or(0)
+    }
+
+    fn callback(&self, action: TapDanceAction) -> Option<&CompiledKeyMapping> {
+        match action {
+            TapDanceAction::Tap(count) => self.taps.get(&count),
+            // holding falls back to tapping if there's no dedicated hold callback
+            TapDanceAction::Hold(count) => self.holds.get(&count).or_else(|| self.taps.get(&count)),
+        }
+    }
+}
+
+struct PendingTapDance {
+    key: Key,
+    device: Arc<DeviceInfo>,
+    // completed taps
+    count: u32,
+    pressed: bool,
+    timer_id: usize,
+    // events that arrived while the dance was pending
+    buffer: Vec<(Arc<DeviceInfo>, InputEvent)>,
+}
+
+/// How the input stage should continue with an event.
+#[derive(Debug, PartialEq)]
+pub enum TapDanceStep {
+    /// the event isn't part of a tap dance
+    Pass,
+    /// the event was consumed or buffered
+    Swallow,
+    /// the event was consumed, the dance resolves if the timer with the given id expires first
+    StartTimer(usize),
+    /// the dance can be resolved, the event was buffered if it interrupted the dance
+    Resolve,
+    /// a key whose hold callback was dispatched got released
+    Released,
+}
+
+pub struct ResolvedTapDance {
+    pub key: Key,
+    pub device: Arc<DeviceInfo>,
+    pub action: TapDanceAction,
+    pub callback: Option<CompiledKeyMapping>,
+    // notifies the hold callback once the key is released
+    pub invocation: Option<KeyPressInvocation>,
+    // the presses of the key if the dance has no callback for them, they bypass the tap dance
+    pub replay: Vec<InputEvent>,
+    pub buffer: Vec<(Arc<DeviceInfo>, InputEvent)>,
+}
+
+/// Keys that dispatch to different callbacks depending on how often they are tapped and whether the last
+/// press is held.
+///
+/// While a dance is pending, all other input events are buffered. The dance resolves once the timeout
+/// passes without another press, the highest defined tap count is reached or another key is pressed, the
+/// buffered events are replayed after the callback.
+pub struct TapDances {
+    timeout: u64,
+    definitions: HashMap<Key, TapDanceDefinition>,
+    pending: Option<PendingTapDance>,
+    // keys whose hold callback was dispatched, their events are consumed until they are released
+    held: HashMap<Key, watch::Sender<bool>>,
+    // keys replayed from a buffer that are still held, their events are queued behind the same callback
+    replayed: HashMap<Key, Key>,
+    next_timer_id: usize,
+}
+
+impl TapDances {
+    pub fn new() -> Self {
+        TapDances {
+            timeout: 200,
+            definitions: Default::default(),
+            pending: None,
+            held: Default::default(),
+            replayed: Default::default(),
+            next_timer_id: 0,
+        }
+    }
+
+    pub fn timeout(&self) -> u64 { self.timeout }
+
+    pub fn set_timeout(&mut self, timeout: u64) { self.timeout = timeout; }
+
+    pub fn insert(&mut self, key: Key, action: TapDanceAction, mapping: CompiledKeyMapping) {
+        let definition = self.definitions.entry(key)
+            .or_insert_with(|| TapDanceDefinition { taps: Default::default(), holds: Default::default() });
+        match action {
+            TapDanceAction::Tap(count) => definition.taps.insert(count, mapping),
+            TapDanceAction::Hold(count) => definition.holds.insert(count, mapping),
+        };
+    }
+
+    pub fn handle_event(&mut self, device: &Arc<DeviceInfo>, ev: &InputEvent) -> TapDanceStep {
+        let key = match ev.event_code {
+            EventCode::EV_KEY(_) => Key { event_code: ev.event_code },
+            _ => {
+                return match &mut self.pending {
+                    Some(pending) => {
+                        pending.buffer.push((device.clone(), ev.clone()));
+                        TapDanceStep::Swallow
+                    }
+                    None => TapDanceStep::Pass,
+                };
+            }
+        };
+
+        if self.held.contains_key(&key) {
+            if ev.value == TYPE_UP {
+                // dropping the sender releases the hold callback
+                self.held.remove(&key);
+                return TapDanceStep::Released;
+            }
+            return TapDanceStep::Swallow;
+        }
+
+        if let Some(pending) = &mut self.pending {
+            if pending.key != key {
+                pending.buffer.push((device.clone(), ev.clone()));
+                // pressing another key interrupts the dance
+                return if ev.value == TYPE_DOWN { TapDanceStep::Resolve } else { TapDanceStep::Swallow };
+            }
+        }
+
+        let max_count = match self.definitions.get(&key) {
+            Some(definition) => definition.max_count(),
+            None => return TapDanceStep::Pass,
+        };
+
+        let timer_id = self.next_timer_id;
+        if ev.value == TYPE_DOWN {
+            let pending = self.pending.get_or_insert_with(|| PendingTapDance {
+                key,
+                device: device.clone(),
+                count: 0,
+                pressed: false,
+                timer_id,
+                buffer: vec![],
+            });
+            pending.pressed = true;
+            pending.timer_id = timer_id;
+        } else {
+            let pending = match &mut self.pending {
+                Some(pending) => pending,
+                // the key was pressed before the dance was defined
+                None => return TapDanceStep::Pass,
+            };
+            if ev.value != TYPE_UP { return TapDanceStep::Swallow; }
+
+            pending.pressed = false;
+            pending.count += 1;
+            if pending.count >= max_count { return TapDanceStep::Resolve; }
+            pending.timer_id = timer_id;
+        }
+
+        self.next_timer_id += 1;
+        TapDanceStep::StartTimer(timer_id)
+    }
+
+    /// Whether the pending dance is still waiting on the given timer.
+    pub fn is_timer_active(&self, timer_id: usize) -> bool {
+        self.pending.as_ref().map_or(false, |pending| pending.timer_id == timer_id)
+    }
+
+    pub fn resolve(&mut self) -> Option<ResolvedTapDance> {
+        let pending = self.pending.take()?;
+
+        let action = if pending.pressed {
+            TapDanceAction::Hold(pending.count + 1)
+        } else {
+            TapDanceAction::Tap(pending.count)
+        };
+
+        let callback = self.definitions.get(&pending.key)
+            .and_then(|definition| definition.callback(action))
+            .cloned();
+
+        let tap = |key| vec![
+            KeyAction::new(key, TYPE_DOWN).to_input_ev(),
+            SYN_REPORT.clone(),
+            KeyAction::new(key, TYPE_UP).to_input_ev(),
+            SYN_REPORT.clone(),
+        ];
+        let (invocation, replay) = match (action, &callback) {
+            (TapDanceAction::Hold(_), Some(_)) => {
+                let (release_tx, invocation) = KeyPressInvocation::new(false);
+                self.held.insert(pending.key, release_tx);
+                (Some(invocation), vec![])
+            }
+            (TapDanceAction::Tap(_), Some(_)) => (None, vec![]),
+            // without a callback the key behaves as if it had no tap dance, the held press is passed through
+            // along with its release
+            (TapDanceAction::Tap(count), None) => (None, (0..count).flat_map(|_| tap(pending.key)).collect()),
+            (TapDanceAction::Hold(count), None) => {
+                let mut replay = (1..count).flat_map(|_| tap(pending.key)).collect::<Vec<_>>();
+                replay.push(KeyAction::new(pending.key, TYPE_DOWN).to_input_ev());
+                replay.push(SYN_REPORT.clone());
+                (None, replay)
+            }
+        };
+
+        Some(ResolvedTapDance {
+            key: pending.key,
+            device: pending.device,
+            action,
+            callback,
+            invocation,
+            replay,
+            buffer: pending.buffer,
+        })
+    }
+
+    /// Gets the callback queue the events of a key need to be ordered behind.
+    ///
+    /// Events replayed from a buffer are queued behind the callback of the dance, the remaining events of
+    /// the same key press follow them.
+    pub fn replay_queue(&mut self, ev: &InputEvent, queue: Option<Key>) -> Option<Key> {
+        let key = Key { event_code: ev.event_code };
+        match queue {
+            Some(queue) => {
+                if ev.value == TYPE_DOWN { self.replayed.insert(key, queue); }
+                Some(queue)
+            }
+            None if ev.value == TYPE_UP => self.replayed.remove(&key),
+            None => self.replayed.get(&key).copied(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::helpers::*;
+
+    use super::*;
+
+    fn tap_dances() -> TapDances {
+        let mut tap_dances = TapDances::new();
+        tap_dances.insert(*KEY_ESC, TapDanceAction::Tap(1), mapping());
+        tap_dances.insert(*KEY_ESC, TapDanceAction::Tap(2), mapping());
+        tap_dances.insert(*KEY_ESC, TapDanceAction::Hold(1), mapping());
+        tap_dances
+    }
+
+    #[test]
+    fn test_tap_count() {
+        let device = keyboard();
+        let mut tap_dances = tap_dances();
+
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN)), TapDanceStep::StartTimer(0));
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP)), TapDanceStep::StartTimer(1));
+        assert!(!tap_dances.is_timer_active(0));
+        assert!(tap_dances.is_timer_active(1));
+
+        // the highest tap count resolves right away
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN)), TapDanceStep::StartTimer(2));
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP)), TapDanceStep::Resolve);
+        let resolved = tap_dances.resolve().unwrap();
+        assert_eq!(resolved.action, TapDanceAction::Tap(2));
+        assert!(resolved.callback.is_some());
+        assert!(!tap_dances.is_timer_active(2));
+
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_A, TYPE_DOWN)), TapDanceStep::Pass);
+    }
+
+    #[test]
+    fn test_interrupted_hold() {
+        let device = keyboard();
+        let mut tap_dances = tap_dances();
+
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN));
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_REPEAT)), TapDanceStep::Swallow);
+        assert_eq!(tap_dances.handle_event(&device, &SYN_REPORT.clone()), TapDanceStep::Swallow);
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_A, TYPE_DOWN)), TapDanceStep::Resolve);
+
+        let resolved = tap_dances.resolve().unwrap();
+        assert_eq!(resolved.action, TapDanceAction::Hold(1));
+        assert_eq!(resolved.buffer.len(), 2);
+        let invocation = resolved.invocation.unwrap();
+        assert!(!invocation.is_released());
+
+        // the held key is consumed until it's released
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_REPEAT)), TapDanceStep::Swallow);
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP)), TapDanceStep::Released);
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_A, TYPE_UP)), TapDanceStep::Pass);
+    }
+
+    #[test]
+    fn test_replay_without_callback() {
+        let device = keyboard();
+        let mut tap_dances = TapDances::new();
+        tap_dances.insert(*KEY_ESC, TapDanceAction::Tap(2), mapping());
+
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN));
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP));
+        let resolved = tap_dances.resolve().unwrap();
+        assert_eq!(resolved.action, TapDanceAction::Tap(1));
+        assert!(resolved.callback.is_none());
+        assert_eq!(resolved.replay, vec![ev(*KEY_ESC, TYPE_DOWN), SYN_REPORT.clone(), ev(*KEY_ESC, TYPE_UP), SYN_REPORT.clone()]);
+
+        // an unhandled hold is pressed again, the release passes through
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN));
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP));
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN));
+        let resolved = tap_dances.resolve().unwrap();
+        assert_eq!(resolved.action, TapDanceAction::Hold(2));
+        assert!(resolved.callback.is_some());
+
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP));
+        tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_DOWN));
+        let resolved = tap_dances.resolve().unwrap();
+        assert_eq!(resolved.action, TapDanceAction::Hold(1));
+        assert_eq!(resolved.replay, vec![ev(*KEY_ESC, TYPE_DOWN), SYN_REPORT.clone()]);
+        assert!(resolved.invocation.is_none());
+        assert_eq!(tap_dances.handle_event(&device, &ev(*KEY_ESC, TYPE_UP)), TapDanceStep::Pass);
+    }
+}
@@ -0,0 +1,22 @@
+use crate::*;
+
+/// An input event of the given key.
+pub fn ev(key: Key, value: i32) -> InputEvent { KeyAction::new(key, value).to_input_ev() }
+
+/// A mapping with an empty body.
+pub fn mapping() -> CompiledKeyMapping {
+    Arc::new((Block::new(), GuardedVarMap::new(Mutex::new(VarMap::new(None))), MappingOptions::default()))
+}
+
+/// A device without absolute axes.
+pub fn device(path: &str, name: &str) -> DeviceInfo {
+    DeviceInfo { path: path.to_string(), name: name.to_string(), vendor: 0, product: 0, abs_ranges: vec![], gamepad: false }
+}
+
+/// The keyboard the events are read from by default.
+pub fn keyboard() -> Arc<DeviceInfo> { Arc::new(device("/dev/input/event0", "Test Keyboard")) }
+
+/// A game controller with the given absolute axes.
+pub fn gamepad(abs_ranges: Vec<(EventCode, i32, i32)>) -> DeviceInfo {
+    DeviceInfo { abs_ranges, gamepad: true, ..device("/dev/input/event5", "Test Gamepad") }
+}
@@ -1,3 +1,4 @@
+pub mod helpers;
 mod script_testing;
 use script_testing::*;
 
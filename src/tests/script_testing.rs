@@ -9,8 +9,11 @@ pub struct ScriptTestingParameters<'a> {
 pub struct ScriptTestingAPI {
     pub event_delay: Option<u64>,
 
-    ev_reader_tx: mpsc::Sender<InputEvent>,
-    ev_writer_rx: mpsc::Receiver<InputEvent>,
+    default_device: Arc<DeviceInfo>,
+    ev_reader_tx: mpsc::Sender<DeviceInputEvent>,
+    ev_writer_rx: mpsc::Receiver<OutputEvent>,
+    window_ev_tx: mpsc::Sender<ActiveWindowInfo>,
+    execution_message_tx: ExecutionMessageSender,
     stop_tx: futures_intrusive::channel::shared::Sender<()>,
     stdout: Arc<tokio::sync::Mutex<Vec<u8>>>,
 }
@@ -25,11 +28,16 @@ impl ScriptTestingAPI {
     }
 
     pub async fn write_event(&mut self, ev: InputEvent) -> Result<()> {
+        let device = self.default_device.clone();
+        self.write_device_event(device, ev).await
+    }
+
+    pub async fn write_device_event(&mut self, device: Arc<DeviceInfo>, ev: InputEvent) -> Result<()> {
         if let Some(delay) = self.event_delay {
             sleep(delay);
         }
 
-        self.ev_reader_tx.send(ev).await?;
+        self.ev_reader_tx.send((device, ev)).await?;
         Ok(())
     }
 
@@ -37,10 +45,24 @@ impl ScriptTestingAPI {
         self.write_event(action.to_input_ev()).await
     }
 
+    #[allow(unused)]
+    pub async fn set_active_window(&mut self, window: ActiveWindowInfo) -> Result<()> {
+        self.window_ev_tx.send(window).await?;
+        Ok(())
+    }
+
+    /// Reports a change of the lock state, like the system does through the virtual output device.
+    #[allow(unused)]
+    pub async fn set_lock_state(&mut self, led: Key, locked: bool) -> Result<()> {
+        self.execution_message_tx.send(ExecutionMessage::LockStateChanged(led, locked)).await
+            .map_err(|_| anyhow!("failed to send message"))?;
+        Ok(())
+    }
+
     pub async fn collect_output_ev(&mut self) -> Vec<InputEvent> {
         let mut vec = vec![];
         while let Ok(ev) = self.ev_writer_rx.try_recv() {
-            vec.push(ev);
+            vec.push(ev.ev);
         }
         vec
     }
@@ -62,6 +84,7 @@ pub async fn test_script(
     let mut script_file = fs::File::open(parameters.script_path)?;
 
     let script_ast = script::parse_script(&mut script_file);
+    let configuration = cli::Configuration { script_file, verbosity: 0, devices: vec![], gamepad: false };
 
     let mut state = State::new();
     let window_cycle_token: usize = 0;
@@ -72,6 +95,9 @@ pub async fn test_script(
     let (execution_message_tx, mut execution_message_rx) = mpsc::channel(128);
     let (ev_reader_tx, mut ev_reader_rx) = mpsc::channel(128);
     let (mut ev_writer_tx, ev_writer_rx) = mpsc::channel(128);
+    let (window_ev_tx, mut window_ev_rx) = mpsc::channel(128);
+
+    let script_ev_writer_tx = ev_writer_tx.clone();
 
     let (stop_tx, stop_rx) = futures_intrusive::channel::shared::unbuffered_channel();
     {
@@ -80,16 +106,19 @@ pub async fn test_script(
         task::spawn(async move {
             loop {
                 tokio::select! {
-                        Some(ev) = ev_reader_rx.recv() => {
-                            event_handlers::handle_stdin_ev(&mut state, ev, &mut mappings,
-                                &mut ev_writer_tx, &mut execution_message_tx, window_cycle_token).await.unwrap();
+                        Some(window) = window_ev_rx.recv() => {
+                            state.active_window = Some(window);
+                        }
+                        Some((device, ev)) = ev_reader_rx.recv() => {
+                            event_handlers::handle_stdin_ev(&mut state, ev, &device, &mut mappings,
+                                &mut ev_writer_tx, &mut execution_message_tx, window_cycle_token, &configuration).await.unwrap();
                         }
                         Some(msg) = execution_message_rx.recv() => {
                             // don't terminate during testing
                             if let ExecutionMessage::Exit(_) = msg{ return; }
 
                             event_handlers::handle_execution_message(&mut *stdout.lock().await, window_cycle_token, msg, &mut state,
-                                &mut mappings, &mut window_change_handlers).await;
+                                &mut mappings, &mut ev_writer_tx, &mut execution_message_tx, &mut window_change_handlers).await;
                         }
                         Some(_) = stop_rx.receive() => {
                             return;
@@ -99,11 +128,16 @@ pub async fn test_script(
         });
     }
 
-    script::evaluate_script(script_ast, execution_message_tx, ev_reader_tx.clone(), 0).await;
+    script::evaluate_script(script_ast, execution_message_tx.clone(), script_ev_writer_tx, 0).await;
+
+    let default_device = crate::tests::helpers::keyboard();
 
     let api = ScriptTestingAPI {
+        default_device,
         ev_reader_tx,
         ev_writer_rx,
+        window_ev_tx,
+        execution_message_tx,
         stop_tx,
         stdout,
         event_delay: None,
@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::watch;
+
+use crate::*;
+
+struct TurboJob {
+    // dropping the sender stops the repetition
+    _stop_tx: watch::Sender<bool>,
+    finished: Arc<AtomicBool>,
+}
+
+/// Repeats key sequences at a fixed rate until they are stopped.
+///
+/// Each sequence is written as a whole before waiting for the next repetition, so stopping it never
+/// leaves keys pressed. The repetition also stops if the system was suspended in the meantime.
+pub struct Turbo {
+    jobs: HashMap<String, TurboJob>,
+}
+
+impl Turbo {
+    pub fn new() -> Self {
+        Turbo { jobs: Default::default() }
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        self.jobs.get(id).map_or(false, |job| !job.finished.load(Ordering::SeqCst))
+    }
+
+    /// Starts repeating the actions, a repetition with the same id is replaced.
+    pub fn start(&mut self, id: String, actions: Vec<KeyAction>, interval: time::Duration, jitter: time::Duration,
+                 ev_writer: mpsc::Sender<OutputEvent>) {
+        let (stop_tx, mut stop_rx) = watch::channel(false);
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let job_finished = finished.clone();
+        task::spawn(async move {
+            let mut rng = Xorshift::from_time();
+            loop {
+                for action in actions.iter() {
+                    ev_writer.send(action.to_input_ev().into()).await.unwrap();
+                    ev_writer.send(SYN_REPORT.clone().into()).await.unwrap();
+                }
+
+                let (monotonic, wall) = (time::Instant::now(), time::SystemTime::now());
+                tokio::select! {
+                    _ = tokio::time::sleep(jittered_interval(interval, jitter, &mut rng)) => {}
+                    _ = stop_rx.changed() => break,
+                }
+
+                if was_suspended(monotonic, wall) { break; }
+            }
+            job_finished.store(true, Ordering::SeqCst);
+        });
+
+        self.jobs.insert(id, TurboJob { _stop_tx: stop_tx, finished });
+    }
+
+    pub fn stop(&mut self, id: &str) { self.jobs.remove(id); }
+
+    pub fn stop_all(&mut self) { self.jobs.clear(); }
+}
+
+// the monotonic clock doesn't advance while the system is suspended, the wall clock does
+fn was_suspended(monotonic: time::Instant, wall: time::SystemTime) -> bool {
+    match wall.elapsed() {
+        Ok(wall_elapsed) => wall_elapsed > monotonic.elapsed() + time::Duration::from_secs(2),
+        Err(_) => false,
+    }
+}
+
+/// Offsets the interval by a random duration of up to +/- the jitter.
+fn jittered_interval(interval: time::Duration, jitter: time::Duration, rng: &mut Xorshift) -> time::Duration {
+    if jitter.as_nanos() == 0 { return interval; }
+    let offset = (rng.next_f64() * 2.0 - 1.0) * jitter.as_secs_f64();
+    time::Duration::try_from_secs_f64((interval.as_secs_f64() + offset).max(0.0)).unwrap_or(interval)
+}
+
+/// A small pseudo random number generator, the jitter doesn't need to be unpredictable.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn from_time() -> Self {
+        let seed = time::SystemTime::now().duration_since(time::UNIX_EPOCH)
+            .map(|v| v.as_nanos() as u64)
+            .unwrap_or(0);
+        Xorshift(seed | 1)
+    }
+
+    /// Gets a number in the range [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jittered_interval() {
+        let mut rng = Xorshift(42);
+        let interval = time::Duration::from_millis(50);
+
+        assert_eq!(jittered_interval(interval, time::Duration::from_millis(0), &mut rng), interval);
+        for _ in 0..100 {
+            let delay = jittered_interval(interval, time::Duration::from_millis(10), &mut rng).as_secs_f64() * 1000.0;
+            assert!(delay > 39.9 && delay < 60.1);
+        }
+
+        // the delay is clamped instead of becoming negative, which would panic
+        for _ in 0..100 {
+            jittered_interval(interval, time::Duration::from_millis(100), &mut rng);
+        }
+    }
+
+    #[test]
+    fn test_suspend_detection() {
+        let now = (time::Instant::now(), time::SystemTime::now());
+        assert!(!was_suspended(now.0, now.1));
+        assert!(was_suspended(now.0, now.1 - time::Duration::from_secs(60)));
+    }
+}